@@ -1,121 +1,712 @@
 //! All programs related to the lambda calculus language.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::str::FromStr;
 use std::sync::RwLock;
 
+use chumsky::span::SimpleSpan;
+
+use crate::encoding::View;
+use crate::environment::Environment;
+use crate::krivine;
+use crate::lazy;
+use crate::parser::Spanned;
+use crate::printer::{Style, print_marked};
+use crate::systemf::{self, TypedTerm};
+use crate::types::{self, TypeError};
+
+/// The deepest term the substitution evaluator reduces further.
+///
+/// Its steps, printing and conversions recurse on the structure of terms, so
+/// a term growing deeper at each step would exhaust the stack before the step
+/// limit.
+pub const MAX_DEPTH: usize = 2000;
+
 /// A lambda calculus term.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum LambdaTerm {
     /// A variable, represented by its name.
     Variable(String),
 
     /// A lambda abstraction, represented by its parameter and body. (\x.body)
-    LambdaAbstraction(String, Box<LambdaTerm>),
+    LambdaAbstraction(String, Box<Self>),
 
     /// An application, represented by its function and argument. (func arg)
-    Application(Box<LambdaTerm>, Box<LambdaTerm>),
+    Application(Box<Self>, Box<Self>),
+}
+
+/// A reduction strategy, deciding which redex is contracted at each step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strategy {
+    /// Contract the leftmost-outermost redex, under lambdas too. Reaches the
+    /// normal form whenever one exists.
+    #[default]
+    NormalOrder,
+
+    /// Contract the leftmost-innermost redex, under lambdas too. Arguments
+    /// are reduced to normal form before being substituted.
+    ApplicativeOrder,
+
+    /// Contract the leftmost-outermost redex, never under a lambda nor inside
+    /// an argument. Stops at a weak head normal form.
+    CallByName,
+
+    /// Reduce the function then the argument to values before contracting,
+    /// never under a lambda. Stops at a weak normal form.
+    CallByValue,
+
+    /// Contract only the head redex, under lambdas too. Stops at a head
+    /// normal form.
+    Head,
+}
+
+impl Strategy {
+    /// All the strategies, with the name used to select them.
+    pub const ALL: [(&str, Self); 5] = [
+        ("normal", Self::NormalOrder),
+        ("applicative", Self::ApplicativeOrder),
+        ("cbn", Self::CallByName),
+        ("cbv", Self::CallByValue),
+        ("head", Self::Head),
+    ];
+
+    /// Whether the strategy reduces inside the body of a lambda abstraction.
+    const fn reduces_under_lambda(self) -> bool {
+        matches!(
+            self,
+            Self::NormalOrder | Self::ApplicativeOrder | Self::Head
+        )
+    }
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, strategy)| *strategy)
+            .ok_or_else(|| {
+                let names = Self::ALL.map(|(name, _)| name).join(", ");
+                format!("unknown strategy '{}', expected one of: {}", s, names)
+            })
+    }
+}
+
+impl Display for Strategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NormalOrder => write!(f, "normal order"),
+            Self::ApplicativeOrder => write!(f, "applicative order"),
+            Self::CallByName => write!(f, "call-by-name"),
+            Self::CallByValue => write!(f, "call-by-value"),
+            Self::Head => write!(f, "head reduction"),
+        }
+    }
+}
+
+/// The normal form an evaluation reduces a term to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Target {
+    /// The beta normal form, where no `(λx.M N)` redex is left.
+    #[default]
+    Beta,
+
+    /// The eta normal form, where no `λx.(M x)` redex with `x` not free in
+    /// `M` is left. Only eta steps are made.
+    Eta,
+
+    /// The beta-eta normal form: the beta normal form, then the eta steps it
+    /// still allows.
+    BetaEta,
+}
+
+impl Target {
+    /// All the targets, with the name used to select them.
+    pub const ALL: [(&str, Self); 3] = [
+        ("beta", Self::Beta),
+        ("eta", Self::Eta),
+        ("betaeta", Self::BetaEta),
+    ];
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, target)| *target)
+            .ok_or_else(|| {
+                let names = Self::ALL.map(|(name, _)| name).join(", ");
+                format!("unknown target '{}', expected one of: {}", s, names)
+            })
+    }
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Beta => write!(f, "β-normal form"),
+            Self::Eta => write!(f, "η-normal form"),
+            Self::BetaEta => write!(f, "βη-normal form"),
+        }
+    }
+}
+
+/// The evaluator `eval` and `assert_eq` instructions reduce terms with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// Rewrite the term one redex at a time, substituting copies of the
+    /// arguments, with the chosen strategy.
+    #[default]
+    Substitution,
+
+    /// Evaluate the term by call-by-need, sharing the evaluation of the
+    /// arguments, then read the result back to its beta normal form.
+    Lazy,
+
+    /// Run the term on the Krivine machine, evaluating it by call-by-name
+    /// with closures, then read the result back to its beta normal form.
+    Krivine,
+}
+
+impl Backend {
+    /// All the backends, with the name used to select them.
+    pub const ALL: [(&str, Self); 3] = [
+        ("substitution", Self::Substitution),
+        ("lazy", Self::Lazy),
+        ("krivine", Self::Krivine),
+    ];
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, backend)| *backend)
+            .ok_or_else(|| {
+                let names = Self::ALL.map(|(name, _)| name).join(", ");
+                format!("unknown backend '{}', expected one of: {}", s, names)
+            })
+    }
+}
+
+impl Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Substitution => write!(f, "substitution"),
+            Self::Lazy => write!(f, "call-by-need"),
+            Self::Krivine => write!(f, "Krivine machine"),
+        }
+    }
+}
+
+/// The parameters of the lambdas enclosing a subterm, as a linked list from
+/// the innermost lambda to the outermost one.
+struct Scope<'a> {
+    /// The parameter of the innermost lambda.
+    name: &'a str,
+
+    /// The parameters of the lambdas enclosing the innermost one.
+    outer: Option<&'a Self>,
+}
+
+impl Scope<'_> {
+    /// Whether the name is bound by one of the lambdas of the scope.
+    fn binds(mut scope: Option<&Self>, name: &str) -> bool {
+        while let Some(current) = scope {
+            if current.name == name {
+                return true;
+            }
+            scope = current.outer;
+        }
+        false
+    }
+}
+
+/// A direction to follow from a term to one of its direct subterms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// The body of a lambda abstraction.
+    Body,
+
+    /// The function of an application.
+    Function,
+
+    /// The argument of an application.
+    Argument,
+}
+
+/// The kind of a reduction step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepKind {
+    /// The contraction of a beta redex `(λx.M N)`.
+    Beta,
+
+    /// The contraction of an eta redex `λx.(M x)`, `x` not being free in `M`.
+    Eta,
+
+    /// The unfolding of a name bound by a `let` instruction.
+    Unfold(String),
+}
+
+impl Display for StepKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Beta => write!(f, "β-reduction"),
+            Self::Eta => write!(f, "η-reduction"),
+            Self::Unfold(name) => write!(f, "unfolding of `{}`", name),
+        }
+    }
+}
+
+/// The description of a single reduction step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReductionStep {
+    /// The kind of the step.
+    pub kind: StepKind,
+
+    /// The path from the reduced term to the contracted redex.
+    pub redex: Vec<Direction>,
+}
+
+impl ReductionStep {
+    /// A step contracting the redex at the root of the term.
+    const fn at_root(kind: StepKind) -> Self {
+        Self {
+            kind,
+            redex: Vec::new(),
+        }
+    }
+
+    /// The same step, made in the subterm in the given direction. The path is
+    /// built from the redex up to the root, so it is reversed once complete.
+    fn inside(mut self, direction: Direction) -> Self {
+        self.redex.push(direction);
+        self
+    }
+
+    /// The step with its path built by `inside` put back in order, from the
+    /// root of the reduced term to the redex.
+    fn rooted(mut self) -> Self {
+        self.redex.reverse();
+        self
+    }
+}
+
+/// The application of a term to an argument.
+fn app(func: LambdaTerm, arg: LambdaTerm) -> LambdaTerm {
+    LambdaTerm::Application(Box::new(func), Box::new(arg))
 }
 
 impl LambdaTerm {
-    /// Perform beta reduction on the lambda term until no more reductions can
-    /// be made.
-    pub fn beta_reduction(self, save_lambda_term: HashMap<String, LambdaTerm>) -> Self {
+    /// Perform beta reduction on the lambda term with the given strategy until
+    /// no more reductions can be made.
+    /// Fails if the term is still reducible after `max_steps` steps, or if it
+    /// comes back to a term it was already reduced from.
+    pub fn beta_reduction(
+        self,
+        strategy: Strategy,
+        max_steps: usize,
+        save_lambda_term: &HashMap<String, Self>,
+    ) -> Result<Self, EvalError> {
+        self.normalize(strategy, Target::Beta, max_steps, save_lambda_term)
+    }
+
+    /// Reduce the lambda term until it reaches the target normal form, the
+    /// beta steps being chosen by the strategy.
+    /// Fails if the term is still reducible after `max_steps` steps, if it
+    /// grows deeper than `MAX_DEPTH`, or if it comes back to a term it was
+    /// already reduced from.
+    pub fn normalize(
+        self,
+        strategy: Strategy,
+        target: Target,
+        max_steps: usize,
+        save_lambda_term: &HashMap<String, Self>,
+    ) -> Result<Self, EvalError> {
+        self.normalize_with(strategy, target, max_steps, save_lambda_term, |_, _| {})
+    }
+
+    /// Reduce the lambda term as in `normalize`, calling `on_step` with the
+    /// term and the description of the step before each step.
+    pub fn normalize_with(
+        self,
+        strategy: Strategy,
+        target: Target,
+        max_steps: usize,
+        save_lambda_term: &HashMap<String, Self>,
+        mut on_step: impl FnMut(&Self, &ReductionStep),
+    ) -> Result<Self, EvalError> {
+        // Only the fingerprints of the terms are kept, with the number of
+        // steps reaching them, the terms of a cycle being rebuilt from the
+        // initial term once a fingerprint comes back
+        let initial = self.clone();
+        let mut seen: HashMap<u64, Vec<usize>> = HashMap::from([(self.fingerprint(), vec![0])]);
         let mut current = self;
-        loop {
-            let reduced = current
-                .clone()
-                .apply_beta_reduction(save_lambda_term.clone());
-            if current == reduced {
-                break;
+        for step_count in 1..=max_steps {
+            let Some((reduced, step)) = current.reduction_step(strategy, target, save_lambda_term)
+            else {
+                return Ok(current);
+            };
+            on_step(&current, &step);
+            if reduced.depth() > MAX_DEPTH {
+                return Err(EvalError::DepthLimit {
+                    max_depth: MAX_DEPTH,
+                    steps: step_count,
+                });
+            }
+            let starts = seen.entry(reduced.fingerprint()).or_default();
+            let normalized = DeBruijnTerm::from(&reduced);
+            for &start in starts.iter() {
+                let mut terms = vec![initial.clone()];
+                for _ in 0..start {
+                    terms[0] = terms[0].reduce_once(strategy, target, save_lambda_term);
+                }
+                if DeBruijnTerm::from(&terms[0]) != normalized {
+                    continue;
+                }
+                for _ in start..step_count {
+                    let next =
+                        terms[terms.len() - 1].reduce_once(strategy, target, save_lambda_term);
+                    terms.push(next);
+                }
+                return Err(EvalError::Cycle { terms });
             }
+            starts.push(step_count);
             current = reduced;
         }
-        current
+        match current.reduction_step(strategy, target, save_lambda_term) {
+            Some(_) => Err(EvalError::StepLimit { max_steps }),
+            None => Ok(current),
+        }
+    }
+
+    /// The hash of the term, equal for the terms that are the same up to the
+    /// renaming of their bound variables.
+    fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        DeBruijnTerm::from(self).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// The term after a step of reduction that is known to be possible.
+    fn reduce_once(
+        &self,
+        strategy: Strategy,
+        target: Target,
+        save_lambda_term: &HashMap<String, Self>,
+    ) -> Self {
+        self.reduction_step(strategy, target, save_lambda_term)
+            .expect("the term was already reduced further")
+            .0
+    }
+
+    /// Apply a single step of reduction towards the target normal form: a
+    /// beta step chosen by the strategy, or an eta step once no beta step is
+    /// left for the beta-eta normal form.
+    pub fn reduction_step(
+        &self,
+        strategy: Strategy,
+        target: Target,
+        save_lambda_term: &HashMap<String, Self>,
+    ) -> Option<(Self, ReductionStep)> {
+        match target {
+            Target::Beta => self.step(strategy, save_lambda_term),
+            Target::Eta => self.eta_step(),
+            Target::BetaEta => self
+                .step(strategy, save_lambda_term)
+                .or_else(|| self.eta_step()),
+        }
+    }
+
+    /// Contract the leftmost-outermost eta redex `λx.(M x)`, where `x` is not
+    /// free in `M`, into `M`.
+    /// Returns the reduced term and the description of the step, or `None` if
+    /// the term is in eta normal form.
+    pub fn eta_step(&self) -> Option<(Self, ReductionStep)> {
+        self.eta_step_from_redex()
+            .map(|(term, step)| (term, step.rooted()))
     }
 
-    /// Apply a single step of beta reduction to the lambda term.
-    fn apply_beta_reduction(self, save_lambda_term: HashMap<String, LambdaTerm>) -> Self {
+    /// Contract an eta redex as `eta_step` does, the path of the step going
+    /// from the redex up to the root.
+    fn eta_step_from_redex(&self) -> Option<(Self, ReductionStep)> {
         match self {
-            LambdaTerm::Variable(v) => {
-                if let Some(lambda) = save_lambda_term.get(&v) {
-                    return lambda.clone();
-                } else {
-                    return LambdaTerm::Variable(v);
+            Self::Variable(_) => None,
+            Self::LambdaAbstraction(param, body) => {
+                if let Self::Application(func, arg) = body.as_ref()
+                    && matches!(arg.as_ref(), Self::Variable(v) if v == param)
+                    && !func.free_variables().contains(param)
+                {
+                    return Some((func.as_ref().clone(), ReductionStep::at_root(StepKind::Eta)));
                 }
+                body.eta_step_from_redex().map(|(body, step)| {
+                    (
+                        Self::LambdaAbstraction(param.clone(), Box::new(body)),
+                        step.inside(Direction::Body),
+                    )
+                })
             }
-            LambdaTerm::LambdaAbstraction(param, body) => LambdaTerm::LambdaAbstraction(
-                param,
-                Box::new(body.apply_beta_reduction(save_lambda_term)),
-            ),
-            LambdaTerm::Application(func, arg) => {
-                let func_reduced = func.apply_beta_reduction(save_lambda_term.clone());
-                let arg_reduced = arg.apply_beta_reduction(save_lambda_term);
+            Self::Application(func, arg) => func
+                .eta_step_from_redex()
+                .map(|(func, step)| {
+                    (
+                        Self::Application(Box::new(func), arg.clone()),
+                        step.inside(Direction::Function),
+                    )
+                })
+                .or_else(|| {
+                    arg.eta_step_from_redex().map(|(arg, step)| {
+                        (
+                            Self::Application(func.clone(), Box::new(arg)),
+                            step.inside(Direction::Argument),
+                        )
+                    })
+                }),
+        }
+    }
+
+    /// The eta expansion `λx.(M x)` of the term `M`, `x` being a variable that
+    /// does not appear in `M`.
+    pub fn eta_expansion(self) -> Self {
+        let param = Self::generate_fresh_variable("x", &self.all_variables(), &self);
+        let body = Self::Application(Box::new(self), Box::new(Self::Variable(param.clone())));
+        Self::LambdaAbstraction(param, Box::new(body))
+    }
+
+    /// Apply a single step of reduction to the lambda term, contracting the
+    /// redex chosen by the strategy. Names bound in `save_lambda_term` are
+    /// unfolded when the strategy reaches one of their free occurrences.
+    /// Returns the reduced term and the description of the step, or `None` if
+    /// the strategy finds no redex to contract.
+    pub fn step(
+        &self,
+        strategy: Strategy,
+        save_lambda_term: &HashMap<String, Self>,
+    ) -> Option<(Self, ReductionStep)> {
+        self.step_in_scope(strategy, save_lambda_term, None)
+            .map(|(term, step)| (term, step.rooted()))
+    }
+
+    /// Apply a single step of reduction as `step` does, `scope` being the
+    /// parameters of the lambdas enclosing the term. The path of the step goes
+    /// from the redex up to the root.
+    fn step_in_scope(
+        &self,
+        strategy: Strategy,
+        save_lambda_term: &HashMap<String, Self>,
+        scope: Option<&Scope<'_>>,
+    ) -> Option<(Self, ReductionStep)> {
+        match self {
+            Self::Variable(v) => {
+                if Scope::binds(scope, v) {
+                    return None;
+                }
+                save_lambda_term.get(v).map(|lambda| {
+                    (
+                        lambda.clone(),
+                        ReductionStep::at_root(StepKind::Unfold(v.clone())),
+                    )
+                })
+            }
+            Self::LambdaAbstraction(param, body) => {
+                if !strategy.reduces_under_lambda() {
+                    return None;
+                }
+                let inner_scope = Scope {
+                    name: param,
+                    outer: scope,
+                };
+                let (body_reduced, step) =
+                    body.step_in_scope(strategy, save_lambda_term, Some(&inner_scope))?;
+
+                // The unfolded definition refers to a global name that this lambda
+                // shadows, so the lambda is renamed before unfolding it.
+                if let StepKind::Unfold(name) = &step.kind
+                    && let Some(definition) = save_lambda_term.get(name)
+                    && definition.free_variables().contains(param)
+                {
+                    let fresh_param =
+                        Self::generate_fresh_variable(param, &self.all_variables(), definition);
+                    let renamed_body = body
+                        .as_ref()
+                        .clone()
+                        .substitute(param, &Self::Variable(fresh_param.clone()));
+                    return Self::LambdaAbstraction(fresh_param, Box::new(renamed_body))
+                        .step_in_scope(strategy, save_lambda_term, scope);
+                }
+
+                Some((
+                    Self::LambdaAbstraction(param.clone(), Box::new(body_reduced)),
+                    step.inside(Direction::Body),
+                ))
+            }
+            Self::Application(func, arg) => {
+                let contract = || match func.as_ref() {
+                    Self::LambdaAbstraction(param, body) => Some((
+                        body.as_ref().clone().substitute(param, arg),
+                        ReductionStep::at_root(StepKind::Beta),
+                    )),
+                    _ => None,
+                };
+                let reduce_func = || {
+                    func.step_in_scope(strategy, save_lambda_term, scope)
+                        .map(|(f, step)| {
+                            (
+                                Self::Application(Box::new(f), arg.clone()),
+                                step.inside(Direction::Function),
+                            )
+                        })
+                };
+                let reduce_arg = || {
+                    arg.step_in_scope(strategy, save_lambda_term, scope)
+                        .map(|(a, step)| {
+                            (
+                                Self::Application(func.clone(), Box::new(a)),
+                                step.inside(Direction::Argument),
+                            )
+                        })
+                };
 
-                match func_reduced {
-                    LambdaTerm::LambdaAbstraction(param, body) => {
-                        body.substitute(&param, &arg_reduced)
+                match strategy {
+                    Strategy::NormalOrder => contract().or_else(reduce_func).or_else(reduce_arg),
+                    Strategy::CallByName | Strategy::Head => contract().or_else(reduce_func),
+                    Strategy::ApplicativeOrder | Strategy::CallByValue => {
+                        reduce_func().or_else(reduce_arg).or_else(contract)
                     }
-                    _ => LambdaTerm::Application(Box::new(func_reduced), Box::new(arg_reduced)),
                 }
             }
         }
     }
 
+    /// Apply at most `count` steps of reduction towards the target normal
+    /// form to the lambda term, stopping early if no step is left to make or
+    /// if the term grew deeper than `MAX_DEPTH`.
+    /// Returns the reduced term and the number of steps made.
+    pub fn steps(
+        self,
+        strategy: Strategy,
+        target: Target,
+        count: usize,
+        save_lambda_term: &HashMap<String, Self>,
+    ) -> (Self, usize) {
+        let mut current = self;
+        for step_count in 0..count {
+            if current.depth() > MAX_DEPTH {
+                return (current, step_count);
+            }
+            match current.reduction_step(strategy, target, save_lambda_term) {
+                Some((reduced, _)) => current = reduced,
+                None => return (current, step_count),
+            }
+        }
+        (current, count)
+    }
+
     /// Substitute all occurrences of a variable with a given lambda term.
     /// Implements alpha conversion to avoid variable capture.
-    fn substitute(self, var: &str, replacement: &LambdaTerm) -> Self {
+    fn substitute(self, var: &str, replacement: &Self) -> Self {
         match self {
-            LambdaTerm::Variable(v) => {
+            Self::Variable(v) => {
                 if v == var {
                     replacement.clone()
                 } else {
-                    LambdaTerm::Variable(v)
+                    Self::Variable(v)
                 }
             }
-            LambdaTerm::LambdaAbstraction(param, body) => {
+            Self::LambdaAbstraction(param, body) => {
                 if param == var {
-                    LambdaTerm::LambdaAbstraction(param, body)
+                    Self::LambdaAbstraction(param, body)
                 } else if replacement.free_variables().contains(&param) {
                     let all_vars =
-                        LambdaTerm::LambdaAbstraction(param.clone(), body.clone()).all_variables();
+                        Self::LambdaAbstraction(param.clone(), body.clone()).all_variables();
                     let fresh_param = Self::generate_fresh_variable(&param, &all_vars, replacement);
                     let renamed_body =
-                        body.substitute(&param, &LambdaTerm::Variable(fresh_param.clone()));
-                    LambdaTerm::LambdaAbstraction(
+                        body.substitute(&param, &Self::Variable(fresh_param.clone()));
+                    Self::LambdaAbstraction(
                         fresh_param,
                         Box::new(renamed_body.substitute(var, replacement)),
                     )
                 } else {
-                    LambdaTerm::LambdaAbstraction(
-                        param,
-                        Box::new(body.substitute(var, replacement)),
-                    )
+                    Self::LambdaAbstraction(param, Box::new(body.substitute(var, replacement)))
                 }
             }
-            LambdaTerm::Application(func, arg) => LambdaTerm::Application(
+            Self::Application(func, arg) => Self::Application(
                 Box::new(func.substitute(var, replacement)),
                 Box::new(arg.substitute(var, replacement)),
             ),
         }
     }
 
+    /// The term `Y (λname.body)`, where `Y = λrec.(λx.rec (x x)) (λx.rec (x x))`
+    /// is the fixpoint combinator: it reduces to the body with `name` standing
+    /// for the term itself, so that the body can refer to it recursively.
+    /// Normal order reduction only unfolds the recursion where it is needed.
+    ///
+    /// The parameter of the combinator is named after the `rec` keyword, which
+    /// cannot be written as a variable, so that [`Self::as_fixpoint`] tells
+    /// the terms built by `let rec` from the ones written by hand.
+    pub fn fixpoint(name: &str, body: Self) -> Self {
+        app(
+            Self::fixpoint_combinator(),
+            Self::LambdaAbstraction(name.to_string(), Box::new(body)),
+        )
+    }
+
+    /// The name and the body of the recursive definition, if the term was
+    /// built by [`Self::fixpoint`].
+    pub fn as_fixpoint(&self) -> Option<(&str, &Self)> {
+        let Self::Application(func, arg) = self else {
+            return None;
+        };
+        let Self::LambdaAbstraction(name, body) = arg.as_ref() else {
+            return None;
+        };
+        (**func == Self::fixpoint_combinator()).then_some((name.as_str(), body.as_ref()))
+    }
+
+    /// The fixpoint combinator `λrec.(λx.rec (x x)) (λx.rec (x x))` inserted by
+    /// `let rec`.
+    pub fn fixpoint_combinator() -> Self {
+        let var = |name: &str| Self::Variable(name.to_string());
+        let half = Self::LambdaAbstraction(
+            "x".to_string(),
+            Box::new(app(var("rec"), app(var("x"), var("x")))),
+        );
+        Self::LambdaAbstraction("rec".to_string(), Box::new(app(half.clone(), half)))
+    }
+
+    /// Rename the free occurrences of a variable, renaming the bound variables
+    /// that would capture the new name.
+    pub fn rename_free_variable(self, name: &str, new_name: &str) -> Self {
+        self.substitute(name, &Self::Variable(new_name.to_string()))
+    }
+
     /// Get all free variables in this lambda term.
-    fn free_variables(&self) -> std::collections::HashSet<String> {
+    pub fn free_variables(&self) -> std::collections::HashSet<String> {
         use std::collections::HashSet;
 
         match self {
-            LambdaTerm::Variable(v) => {
+            Self::Variable(v) => {
                 let mut set = HashSet::new();
                 set.insert(v.clone());
                 set
             }
-            LambdaTerm::LambdaAbstraction(param, body) => {
+            Self::LambdaAbstraction(param, body) => {
                 let mut free_vars = body.free_variables();
                 free_vars.remove(param);
                 free_vars
             }
-            LambdaTerm::Application(func, arg) => {
+            Self::Application(func, arg) => {
                 let mut free_vars = func.free_variables();
                 free_vars.extend(arg.free_variables());
                 free_vars
@@ -128,7 +719,7 @@ impl LambdaTerm {
     fn generate_fresh_variable(
         base_name: &str,
         current_vars: &std::collections::HashSet<String>,
-        replacement: &LambdaTerm,
+        replacement: &Self,
     ) -> String {
         let mut used_vars = current_vars.clone();
         used_vars.extend(replacement.all_variables());
@@ -140,42 +731,606 @@ impl LambdaTerm {
             counter += 1;
             candidate = format!("{}{}", base_name, counter);
         }
-
-        candidate
+
+        candidate
+    }
+
+    /// Whether the two terms are alpha-equivalent, that is, equal up to the
+    /// renaming of bound variables.
+    pub fn alpha_eq(&self, other: &Self) -> bool {
+        DeBruijnTerm::from(self) == DeBruijnTerm::from(other)
+    }
+
+    /// Get all variables (both free and bound) in this lambda term.
+    fn all_variables(&self) -> std::collections::HashSet<String> {
+        match self {
+            Self::Variable(v) => {
+                let mut set = std::collections::HashSet::new();
+                set.insert(v.clone());
+                set
+            }
+            Self::LambdaAbstraction(param, body) => {
+                let mut vars = body.all_variables();
+                vars.insert(param.clone());
+                vars
+            }
+            Self::Application(func, arg) => {
+                let mut vars = func.all_variables();
+                vars.extend(arg.all_variables());
+                vars
+            }
+        }
+    }
+
+    /// Get the variables bound by a lambda abstraction in this lambda term,
+    /// that is, all its variables that are the parameter of a lambda. A name
+    /// can be both bound and free, as `x` in `(x λx.x)`.
+    pub fn bound_variables(&self) -> std::collections::HashSet<String> {
+        let mut bound = self.all_variables();
+        bound.retain(|name| self.binds(name));
+        bound
+    }
+
+    /// Whether a lambda abstraction of this lambda term has the given name as
+    /// its parameter.
+    fn binds(&self, name: &str) -> bool {
+        match self {
+            Self::Variable(_) => false,
+            Self::LambdaAbstraction(param, body) => param == name || body.binds(name),
+            Self::Application(func, arg) => func.binds(name) || arg.binds(name),
+        }
+    }
+
+    /// The number of beta redexes `(λx.M N)` in this lambda term, nested ones
+    /// included.
+    pub fn redex_count(&self) -> usize {
+        match self {
+            Self::Variable(_) => 0,
+            Self::LambdaAbstraction(_, body) => body.redex_count(),
+            Self::Application(func, arg) => {
+                let redex = usize::from(matches!(func.as_ref(), Self::LambdaAbstraction(..)));
+                redex + func.redex_count() + arg.redex_count()
+            }
+        }
+    }
+
+    /// The number of variables, abstractions and applications of this lambda
+    /// term.
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Variable(_) => 1,
+            Self::LambdaAbstraction(_, body) => 1 + body.size(),
+            Self::Application(func, arg) => 1 + func.size() + arg.size(),
+        }
+    }
+
+    /// The number of nested subterms of this lambda term on its longest path
+    /// from the root to a variable, a variable having depth 1. Counted without
+    /// recursion, so that terms too deep to be reduced can be measured.
+    pub fn depth(&self) -> usize {
+        let mut deepest = 0;
+        let mut pending = vec![(self, 1)];
+        while let Some((term, depth)) = pending.pop() {
+            deepest = deepest.max(depth);
+            match term {
+                Self::Variable(_) => {}
+                Self::LambdaAbstraction(_, body) => pending.push((body, depth + 1)),
+                Self::Application(func, arg) => {
+                    pending.push((func, depth + 1));
+                    pending.push((arg, depth + 1));
+                }
+            }
+        }
+        deepest
+    }
+
+    /// The strongest normal form this lambda term is in, names being taken as
+    /// variables and never unfolded.
+    pub fn form(&self) -> Form {
+        let definitions = HashMap::new();
+        let is_stuck = |strategy| self.step(strategy, &definitions).is_none();
+        if is_stuck(Strategy::NormalOrder) {
+            Form::Normal
+        } else if is_stuck(Strategy::Head) {
+            Form::HeadNormal
+        } else if is_stuck(Strategy::CallByName) {
+            Form::WeakHeadNormal
+        } else {
+            Form::Reducible
+        }
+    }
+}
+
+/// The strongest normal form a term is in, each one implying the next ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Form {
+    /// The beta normal form: no redex is left.
+    Normal,
+
+    /// The head normal form `λx1…xn.(y M1 … Mk)`: no redex is left in head
+    /// position.
+    HeadNormal,
+
+    /// The weak head normal form: a lambda abstraction, or an application
+    /// whose head is a variable.
+    WeakHeadNormal,
+
+    /// None of the above: the head of the term is a redex.
+    Reducible,
+}
+
+impl Display for Form {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Normal => write!(f, "β-normal form"),
+            Self::HeadNormal => write!(f, "head normal form, not β-normal"),
+            Self::WeakHeadNormal => write!(f, "weak head normal form, not head normal"),
+            Self::Reducible => write!(f, "not in weak head normal form"),
+        }
+    }
+}
+
+impl Display for LambdaTerm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.styled(Style::Unicode))
+    }
+}
+
+/// A lambda calculus term in the nameless De Bruijn representation.
+///
+/// A bound variable is given by the number of lambdas between it and its
+/// binder, so alpha-equivalent terms have the same representation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DeBruijnTerm {
+    /// A bound variable, represented by its index. (0 is the parameter of the
+    /// innermost enclosing lambda)
+    Bound(usize),
+
+    /// A free variable, represented by its name.
+    Free(String),
+
+    /// A lambda abstraction, represented by its body. (λ.body)
+    Abstraction(Box<Self>),
+
+    /// An application, represented by its function and argument. (func arg)
+    Application(Box<Self>, Box<Self>),
+}
+
+impl DeBruijnTerm {
+    /// The names given to the parameters of lambdas when converting back to
+    /// a named term, before adding numeric suffixes.
+    const PARAMETER_NAMES: [&str; 6] = ["x", "y", "z", "w", "v", "u"];
+
+    /// Convert a named lambda term to the De Bruijn representation.
+    /// The context holds the names of the variables bound outside of the
+    /// term, the innermost binder first: the variable named `context[i]` gets
+    /// the index `i` plus the number of lambdas of the term enclosing it.
+    /// Other free variables stay named.
+    pub fn from_lambda_term(term: &LambdaTerm, context: &[String]) -> Self {
+        let mut names: Vec<&str> = context.iter().rev().map(String::as_str).collect();
+        Self::from_lambda_term_with(term, &mut names)
+    }
+
+    /// Convert a named lambda term as `from_lambda_term` does, `names` being
+    /// the bound names in scope, the innermost binder last.
+    fn from_lambda_term_with<'a>(term: &'a LambdaTerm, names: &mut Vec<&'a str>) -> Self {
+        match term {
+            LambdaTerm::Variable(v) => names.iter().rposition(|name| name == v).map_or_else(
+                || Self::Free(v.clone()),
+                |position| Self::Bound(names.len() - 1 - position),
+            ),
+            LambdaTerm::LambdaAbstraction(param, body) => {
+                names.push(param);
+                let body = Self::from_lambda_term_with(body, names);
+                names.pop();
+                Self::Abstraction(Box::new(body))
+            }
+            LambdaTerm::Application(func, arg) => Self::Application(
+                Box::new(Self::from_lambda_term_with(func, names)),
+                Box::new(Self::from_lambda_term_with(arg, names)),
+            ),
+        }
+    }
+
+    /// Convert the term back to a named lambda term, in the given context (see
+    /// `from_lambda_term`). Lambdas get fresh parameter names that clash
+    /// neither with free variables nor with the names in scope.
+    /// Returns `None` if a bound variable points outside of the context.
+    pub fn to_lambda_term(&self, context: &[String]) -> Option<LambdaTerm> {
+        let mut names: Vec<String> = context.iter().rev().cloned().collect();
+        let mut used = self.free_names();
+        used.extend(context.iter().cloned());
+        self.to_lambda_term_with(&mut names, &used)
+    }
+
+    /// Convert the term back as `to_lambda_term` does, `names` being the bound
+    /// names in scope, the innermost binder last, and `used` the names that
+    /// lambdas must not take.
+    fn to_lambda_term_with(
+        &self,
+        names: &mut Vec<String>,
+        used: &HashSet<String>,
+    ) -> Option<LambdaTerm> {
+        match self {
+            Self::Bound(index) => {
+                let position = names.len().checked_sub(index + 1)?;
+                Some(LambdaTerm::Variable(names[position].clone()))
+            }
+            Self::Free(name) => Some(LambdaTerm::Variable(name.clone())),
+            Self::Abstraction(body) => {
+                let param = (0..)
+                    .map(|n: usize| {
+                        let base = Self::PARAMETER_NAMES[n % Self::PARAMETER_NAMES.len()];
+                        match n / Self::PARAMETER_NAMES.len() {
+                            0 => base.to_string(),
+                            suffix => format!("{}{}", base, suffix),
+                        }
+                    })
+                    .find(|name| !used.contains(name) && !names.contains(name))?;
+                names.push(param.clone());
+                let body = body.to_lambda_term_with(names, used);
+                names.pop();
+                Some(LambdaTerm::LambdaAbstraction(param, Box::new(body?)))
+            }
+            Self::Application(func, arg) => Some(LambdaTerm::Application(
+                Box::new(func.to_lambda_term_with(names, used)?),
+                Box::new(arg.to_lambda_term_with(names, used)?),
+            )),
+        }
+    }
+
+    /// Get the names of all free variables in this term.
+    fn free_names(&self) -> HashSet<String> {
+        match self {
+            Self::Bound(_) => HashSet::new(),
+            Self::Free(name) => HashSet::from([name.clone()]),
+            Self::Abstraction(body) => body.free_names(),
+            Self::Application(func, arg) => {
+                let mut names = func.free_names();
+                names.extend(arg.free_names());
+                names
+            }
+        }
+    }
+
+    /// Add `amount` to the indices of the bound variables that point outside
+    /// of the term, `cutoff` being the number of enclosing lambdas of the term
+    /// they are bound by otherwise.
+    pub fn shift(&self, amount: isize, cutoff: usize) -> Self {
+        match self {
+            Self::Bound(index) if *index >= cutoff => Self::Bound(
+                index
+                    .checked_add_signed(amount)
+                    .expect("shifting a variable out of its context"),
+            ),
+            Self::Bound(_) | Self::Free(_) => self.clone(),
+            Self::Abstraction(body) => Self::Abstraction(Box::new(body.shift(amount, cutoff + 1))),
+            Self::Application(func, arg) => Self::Application(
+                Box::new(func.shift(amount, cutoff)),
+                Box::new(arg.shift(amount, cutoff)),
+            ),
+        }
+    }
+}
+
+impl From<&LambdaTerm> for DeBruijnTerm {
+    fn from(term: &LambdaTerm) -> Self {
+        Self::from_lambda_term(term, &[])
+    }
+}
+
+impl Display for DeBruijnTerm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bound(index) => write!(f, "{}", index),
+            Self::Free(name) => write!(f, "{}", name),
+            Self::Abstraction(body) => write!(f, "λ.{}", body),
+            Self::Application(func, arg) => write!(f, "({} {})", func, arg),
+        }
+    }
+}
+
+/// How the reduction is described in the output of the instructions: by its
+/// strategy, and by its target unless it is the beta normal form. Eta steps
+/// do not depend on the strategy.
+fn reduction_label(strategy: Strategy, target: Target) -> String {
+    match target {
+        Target::Beta => strategy.to_string(),
+        Target::Eta => target.to_string(),
+        Target::BetaEta => format!("{}, {}", strategy, target),
+    }
+}
+
+/// How an evaluation by a machine is described in the output of the
+/// instructions, as `reduction_label` does for the substitution evaluator.
+fn backend_label(backend: Backend, target: Target) -> String {
+    match target {
+        Target::BetaEta => format!("{}, {}", backend, target),
+        _ => backend.to_string(),
+    }
+}
+
+/// An error raised while evaluating an instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalError {
+    /// The term is still reducible after the maximum number of steps.
+    StepLimit {
+        /// The maximum number of steps that was allowed.
+        max_steps: usize,
+    },
+
+    /// The term grew too deep to be reduced further before reaching a normal
+    /// form.
+    DepthLimit {
+        /// The maximum depth of the terms that are reduced.
+        max_depth: usize,
+
+        /// The number of steps made before the term grew too deep.
+        steps: usize,
+    },
+
+    /// The instruction chooses how to reduce the term, which the backend of
+    /// the settings cannot follow.
+    UnsupportedByBackend {
+        /// The backend of the settings.
+        backend: Backend,
+
+        /// The description of the option of the instruction.
+        option: String,
+    },
+
+    /// The two sides of an `assert_eq` instruction have different normal
+    /// forms.
+    AssertionFailed {
+        /// The normal form of the left side.
+        left: LambdaTerm,
+
+        /// The normal form of the right side.
+        right: LambdaTerm,
+    },
+
+    /// The reduction came back to a term it was already reduced from.
+    Cycle {
+        /// The terms of the cycle, the first and the last ones being
+        /// alpha-equivalent.
+        terms: Vec<LambdaTerm>,
+    },
+
+    /// An unqualified name refers to definitions of several imported modules.
+    AmbiguousName {
+        /// The unqualified name.
+        name: String,
+
+        /// The qualified names it may refer to.
+        candidates: Vec<String>,
+    },
+
+    /// An imported module is not defined.
+    UnknownModule {
+        /// The name of the module.
+        module: String,
+    },
+
+    /// A name imported from a module is not defined in it.
+    NotInModule {
+        /// The name of the module.
+        module: String,
+
+        /// The name imported from the module.
+        name: String,
+
+        /// The names the module defines.
+        defined: Vec<String>,
+    },
+
+    /// The term has no simple type.
+    Type(TypeError),
+
+    /// The explicitly typed term is not well typed in System F.
+    SystemF(systemf::TypeError),
+}
+
+impl From<TypeError> for EvalError {
+    fn from(error: TypeError) -> Self {
+        Self::Type(error)
+    }
+}
+
+impl From<systemf::TypeError> for EvalError {
+    fn from(error: systemf::TypeError) -> Self {
+        Self::SystemF(error)
+    }
+}
+
+impl EvalError {
+    /// The title of the report of the error.
+    pub const fn title(&self) -> &'static str {
+        match self {
+            Self::Type(_) | Self::SystemF(_) => "Type Error",
+            _ => "Evaluation Error",
+        }
+    }
+
+    /// The span of the tokens of the subterm the error is about, if it is not
+    /// about the whole instruction.
+    pub const fn span(&self) -> Option<SimpleSpan> {
+        match self {
+            Self::SystemF(error) => Some(error.span()),
+            _ => None,
+        }
+    }
+
+    /// A hint on how to get rid of the error.
+    pub const fn help(&self) -> &'static str {
+        match self {
+            Self::StepLimit { .. } => {
+                "the term may diverge, otherwise raise the limit with `eval:<steps>` or \
+                 `--max-steps`"
+            }
+            Self::DepthLimit { .. } => {
+                "the term keeps growing as it is reduced, so it likely diverges"
+            }
+            Self::UnsupportedByBackend { .. } => {
+                "remove the option, or evaluate with `--backend substitution`"
+            }
+            Self::AssertionFailed { .. } => {
+                "both sides must reduce to the same term, up to the renaming of bound variables \
+                 (compare up to eta with `assert_eq:betaeta`)"
+            }
+            Self::Cycle { .. } => "this strategy never reaches a normal form, another strategy may",
+            Self::AmbiguousName { .. } => "write the qualified name, as in `Module.name`",
+            Self::UnknownModule { .. } => {
+                "modules are defined with `module Name { ... }` before being imported"
+            }
+            Self::NotInModule { .. } => "only the names bound by `let` in a module are imported",
+            Self::Type(error) => error.help(),
+            Self::SystemF(error) => error.help(),
+        }
+    }
+
+    /// Additional details about the error, with the terms printed in the
+    /// given style.
+    pub fn note(&self, style: Style) -> Option<String> {
+        match self {
+            Self::StepLimit { .. }
+            | Self::DepthLimit { .. }
+            | Self::UnsupportedByBackend { .. } => None,
+            Self::AssertionFailed { left, right } => Some(format!(
+                "the left side reduces to:\n  {}\nthe right side reduces to:\n  {}",
+                left.styled(style),
+                right.styled(style)
+            )),
+            Self::Cycle { terms } => Some(format!(
+                "the cycle is:\n  {}",
+                terms
+                    .iter()
+                    .map(|term| term.styled(style).to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n→ ")
+            )),
+            Self::AmbiguousName { candidates, .. } => Some(format!(
+                "the name may refer to:\n  {}",
+                candidates.join("\n  ")
+            )),
+            Self::UnknownModule { .. } => None,
+            Self::NotInModule {
+                module, defined, ..
+            } => Some(format!(
+                "module `{}` defines: {}",
+                module,
+                defined.join(", ")
+            )),
+            Self::Type(error) => Some(error.note(style)),
+            Self::SystemF(error) => error.note(),
+        }
     }
+}
 
-    /// Get all variables (both free and bound) in this lambda term.
-    fn all_variables(&self) -> std::collections::HashSet<String> {
+impl Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            LambdaTerm::Variable(v) => {
-                let mut set = std::collections::HashSet::new();
-                set.insert(v.clone());
-                set
+            Self::StepLimit { max_steps } => {
+                write!(f, "no normal form within {} steps", max_steps)
             }
-            LambdaTerm::LambdaAbstraction(param, body) => {
-                let mut vars = body.all_variables();
-                vars.insert(param.clone());
-                vars
+            Self::DepthLimit { max_depth, steps } => write!(
+                f,
+                "the term grew deeper than {} nested subterms after {} steps",
+                max_depth, steps
+            ),
+            Self::UnsupportedByBackend { backend, option } => {
+                write!(f, "the {} backend cannot reduce with {}", backend, option)
             }
-            LambdaTerm::Application(func, arg) => {
-                let mut vars = func.all_variables();
-                vars.extend(arg.all_variables());
-                vars
+            Self::AssertionFailed { .. } => write!(f, "the normal forms of both sides differ"),
+            Self::Cycle { terms } => write!(
+                f,
+                "term enters a reduction cycle of length {}",
+                terms.len() - 1
+            ),
+            Self::AmbiguousName { name, .. } => {
+                write!(f, "`{}` is imported from several modules", name)
             }
+            Self::UnknownModule { module } => write!(f, "no module `{}` is defined", module),
+            Self::NotInModule { module, name, .. } => {
+                write!(f, "module `{}` does not define `{}`", module, name)
+            }
+            Self::Type(error) => write!(f, "{}", error),
+            Self::SystemF(error) => write!(f, "{}", error),
         }
     }
 }
 
-impl Display for LambdaTerm {
+/// The global settings of the interpreter, set from the command line.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// The strategy used by `eval` instructions that do not choose one.
+    pub strategy: Strategy,
+
+    /// The maximum number of reduction steps of `eval` instructions that do
+    /// not choose one.
+    pub max_steps: usize,
+
+    /// Whether every evaluation prints its reduction trace.
+    pub trace: bool,
+
+    /// The style terms are printed in.
+    pub style: Style,
+
+    /// Whether the results of evaluations are shown as terms, as the values
+    /// they encode, or both.
+    pub view: View,
+
+    /// Whether the terms of `let` and `eval` instructions are type-checked
+    /// before being bound or reduced.
+    pub typed: bool,
+
+    /// The normal form reached by the evaluations that do not choose one.
+    pub target: Target,
+
+    /// The evaluator of the `eval` and `assert_eq` instructions.
+    pub backend: Backend,
+}
+
+/// The options of an `eval` instruction, overriding the global settings.
+#[derive(Debug, Clone, Default)]
+pub struct EvalOptions {
+    /// The reduction strategy to use.
+    pub strategy: Option<Strategy>,
+
+    /// The maximum number of reduction steps.
+    pub max_steps: Option<usize>,
+
+    /// The normal form to reach.
+    pub target: Option<Target>,
+
+    /// Whether to eta-expand the normal form once, as in `λx.(f x)` for `f`.
+    pub expand: bool,
+
+    /// Whether to print every intermediate term of the reduction.
+    pub trace: bool,
+}
+
+/// A representation in which a term can be shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TermFormat {
+    /// The nameless De Bruijn representation.
+    DeBruijn,
+}
+
+impl FromStr for TermFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "debruijn" => Ok(Self::DeBruijn),
+            _ => Err(format!("unknown format '{}', expected: debruijn", s)),
+        }
+    }
+}
+
+impl Display for TermFormat {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            LambdaTerm::Variable(name) => write!(f, "{}", name),
-            LambdaTerm::LambdaAbstraction(param, body) => {
-                write!(f, "λ{}.{}", param, body)
-            }
-            LambdaTerm::Application(func, arg) => {
-                write!(f, "({} {})", func, arg)
-            }
+            Self::DeBruijn => write!(f, "de Bruijn"),
         }
     }
 }
@@ -190,36 +1345,540 @@ pub enum Instruction {
 
         /// The lambda term to bind to the name.
         lambda_term: LambdaTerm,
+
+        /// Whether the lambda term may refer to the name itself (`let rec`).
+        recursive: bool,
+
+        /// The module the name is defined in, if any. The name is then bound
+        /// qualified, as in `Bool.and`.
+        module: Option<String>,
     },
 
     /// Evaluate the lambda term. (Evaluation is done via beta reduction and
-    /// printing the result)
-    Eval(LambdaTerm),
+    /// printing the result, along with the trace of the reduction for `trace`
+    /// instructions)
+    Eval {
+        /// The lambda term to evaluate.
+        lambda_term: LambdaTerm,
+
+        /// The options of the evaluation.
+        options: EvalOptions,
+    },
+
+    /// Apply a given number of reduction steps to the lambda term and print
+    /// the result, even if it is not in normal form.
+    Step {
+        /// The lambda term to reduce.
+        lambda_term: LambdaTerm,
+
+        /// The number of steps to apply.
+        count: usize,
+
+        /// The reduction strategy to use, if different from the default one.
+        strategy: Option<Strategy>,
+
+        /// The normal form to reduce towards, if different from the default
+        /// one.
+        target: Option<Target>,
+    },
+
+    /// Evaluate two lambda terms and check that their normal forms are
+    /// alpha-equivalent.
+    AssertEq {
+        /// The left side of the assertion.
+        left: LambdaTerm,
+
+        /// The right side of the assertion.
+        right: LambdaTerm,
+
+        /// The options of the evaluation of both sides.
+        options: EvalOptions,
+    },
+
+    /// Infer the principal simple type of the lambda term and print it.
+    Type {
+        /// The lambda term to type.
+        lambda_term: LambdaTerm,
+    },
+
+    /// Print the lambda term, as written, in another representation.
+    Show {
+        /// The lambda term to show.
+        lambda_term: LambdaTerm,
+
+        /// The representation to show the term in.
+        format: TermFormat,
+    },
+
+    /// Print the normal form the lambda term is in, its number of redexes,
+    /// size and depth, and its free and bound variables.
+    Inspect {
+        /// The lambda term to inspect.
+        lambda_term: LambdaTerm,
+    },
+
+    /// Run the instructions of another program, keeping its definitions.
+    Import {
+        /// The program to import.
+        source: ImportSource,
+    },
+
+    /// Make names of a module usable unqualified, as in `import Bool (and)`.
+    Open {
+        /// The name of the module.
+        module: String,
+
+        /// The names to make usable, all the names of the module if `None`.
+        names: Option<Vec<String>>,
+    },
+
+    /// Name a System F type, as in `type Nat = forall a. (a -> a) -> a -> a`.
+    TypeAlias {
+        /// The name of the type.
+        name: String,
+
+        /// The type, with the span of its tokens.
+        ty: Spanned<systemf::Type>,
+    },
+
+    /// Run an instruction whose terms hold types, once they are type-checked
+    /// in System F. The instruction holds the terms with their types erased.
+    Annotated {
+        /// The explicitly typed terms of the instruction.
+        terms: Vec<Spanned<TypedTerm>>,
+
+        /// The instruction to run.
+        instruction: Box<Self>,
+    },
+}
+
+/// A program imported by an `import` or `use` instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportSource {
+    /// A lambca file, given by its path relative to the importing file.
+    File(String),
+
+    /// The standard prelude, embedded in the interpreter.
+    Prelude,
 }
 
 impl Instruction {
-    /// Execute the instruction, modifying the provided hashmap of saved lambda
-    /// terms as needed.
-    /// For `Let` instructions, the hashmap is updated with the new binding.
+    /// Execute the instruction, modifying the provided environment of saved
+    /// lambda terms as needed. The names the terms import from modules are
+    /// resolved first, and an error is returned if one is ambiguous.
+    /// For `Let` instructions, the environment is updated with the new
+    /// binding, the fixpoint of the lambda term for recursive ones.
+    /// In typed mode, the terms of `Let` and `Eval` instructions are first
+    /// type-checked, and an error is returned if they have no simple type.
     /// For `Eval` instructions, the lambda term is beta-reduced and printed to
-    /// the console.
-    pub async fn compute(self, save_lambda_term: &RwLock<HashMap<String, LambdaTerm>>) {
+    /// the console, along with the strategy used and, if asked for, every
+    /// step of the reduction.
+    /// For `Step` instructions, the lambda term is reduced by the given number
+    /// of steps at most and printed to the console.
+    /// For `AssertEq` instructions, both lambda terms are beta-reduced, and
+    /// an error is returned if their normal forms are not alpha-equivalent.
+    /// For `Type` instructions, the principal simple type of the lambda term
+    /// is printed to the console, and an error is returned if it has none.
+    /// For `Show` instructions, the lambda term is printed to the console in
+    /// the given representation.
+    /// For `Inspect` instructions, the report on the lambda term, as written,
+    /// is printed to the console.
+    /// For `Open` instructions, the names of the module are made usable
+    /// unqualified.
+    /// For `TypeAlias` instructions, the type is named, once the aliases it
+    /// refers to are expanded.
+    /// For `Annotated` instructions, the explicitly typed terms are checked
+    /// with the System F rules, and an error pointing at the ill-typed subterm
+    /// is returned if one is not well typed. The instruction is then run on
+    /// the erased terms, without the simple type checking of the typed mode:
+    /// a `Type` instruction prints the System F type, and a `Let` instruction
+    /// records it as the type of the name.
+    /// `Import` instructions do nothing here: they are run by the interpreter,
+    /// which knows where the importing program comes from.
+    pub async fn compute(
+        self,
+        save_lambda_term: &RwLock<Environment>,
+        settings: &Settings,
+    ) -> Result<(), EvalError> {
         match self {
-            Instruction::Let { name, lambda_term } => {
-                let mut write = save_lambda_term.write().unwrap();
-                write.insert(name, lambda_term);
+            Self::Let {
+                name,
+                lambda_term,
+                recursive,
+                module,
+            } => {
+                let lambda_term = if recursive {
+                    LambdaTerm::fixpoint(&name, lambda_term)
+                } else {
+                    lambda_term
+                };
+                let name = match module {
+                    Some(module) => format!("{}.{}", module, name),
+                    None => name,
+                };
+                let mut write = save_lambda_term
+                    .write()
+                    .expect("the saved lambda terms lock is poisoned");
+                let lambda_term = write.resolve(lambda_term)?;
+                if settings.typed {
+                    types::infer(&lambda_term, write.definitions())?;
+                }
+                write.define(name, lambda_term);
+            }
+            Self::Eval {
+                lambda_term,
+                options,
+            } => {
+                let strategy = options.strategy.unwrap_or(settings.strategy);
+                let target = options.target.unwrap_or(settings.target);
+                let max_steps = options.max_steps.unwrap_or(settings.max_steps);
+                let trace = options.trace || settings.trace;
+                let read = save_lambda_term
+                    .read()
+                    .expect("the saved lambda terms lock is poisoned");
+                let lambda_term = read.resolve(lambda_term)?;
+                let definitions = read.definitions();
+                if settings.typed {
+                    types::infer(&lambda_term, definitions)?;
+                }
+                let result = if trace {
+                    Self::trace_reduction(
+                        lambda_term,
+                        (strategy, target),
+                        max_steps,
+                        definitions,
+                        settings.style,
+                    )
+                    .map(|result| (result, None))
+                } else {
+                    Self::evaluate(
+                        lambda_term,
+                        (options.strategy, target),
+                        max_steps,
+                        settings,
+                        definitions,
+                    )
+                };
+                drop(read);
+                let (mut result, work) = result?;
+                if options.expand {
+                    result = result.eta_expansion();
+                }
+                let label = work.map_or_else(
+                    || reduction_label(strategy, target),
+                    |work| format!("{}, {}", backend_label(settings.backend, target), work),
+                );
+                println!(
+                    "[{}] {}",
+                    label,
+                    settings.view.show(&result, settings.style)
+                );
+            }
+            Self::Step {
+                lambda_term,
+                count,
+                strategy,
+                target,
+            } => {
+                let strategy = strategy.unwrap_or(settings.strategy);
+                let target = target.unwrap_or(settings.target);
+                let read = save_lambda_term
+                    .read()
+                    .expect("the saved lambda terms lock is poisoned");
+                let lambda_term = read.resolve(lambda_term)?;
+                let (result, step_count) =
+                    lambda_term.steps(strategy, target, count, read.definitions());
+                drop(read);
+                let plural = if step_count == 1 { "" } else { "s" };
+                let normal_form = if step_count < count {
+                    ", normal form"
+                } else {
+                    ""
+                };
+                println!(
+                    "[{}, {} step{}{}] {}",
+                    reduction_label(strategy, target),
+                    step_count,
+                    plural,
+                    normal_form,
+                    result.styled(settings.style)
+                );
+            }
+            Self::AssertEq {
+                left,
+                right,
+                options,
+            } => {
+                let strategy = options.strategy.unwrap_or(settings.strategy);
+                let target = options.target.unwrap_or(settings.target);
+                let max_steps = options.max_steps.unwrap_or(settings.max_steps);
+                let read = save_lambda_term
+                    .read()
+                    .expect("the saved lambda terms lock is poisoned");
+                let definitions = read.definitions();
+                let normalize = |term: &LambdaTerm| {
+                    read.resolve(term.clone()).and_then(|term| {
+                        Self::evaluate(
+                            term,
+                            (options.strategy, target),
+                            max_steps,
+                            settings,
+                            definitions,
+                        )
+                    })
+                };
+                let left_result = normalize(&left);
+                let right_result = normalize(&right);
+                drop(read);
+                let ((left_reduced, work), (right_reduced, _)) = (left_result?, right_result?);
+                if !left_reduced.alpha_eq(&right_reduced) {
+                    return Err(EvalError::AssertionFailed {
+                        left: left_reduced,
+                        right: right_reduced,
+                    });
+                }
+                let label = work.map_or_else(
+                    || reduction_label(strategy, target),
+                    |_| backend_label(settings.backend, target),
+                );
+                println!(
+                    "[{}] ok: {} = {}",
+                    label,
+                    left.styled(settings.style),
+                    right.styled(settings.style)
+                );
+            }
+            Self::Type { lambda_term } => {
+                let read = save_lambda_term
+                    .read()
+                    .expect("the saved lambda terms lock is poisoned");
+                let lambda_term = read.resolve(lambda_term)?;
+                // A name bound to an annotated term has its System F type
+                if let LambdaTerm::Variable(name) = &lambda_term
+                    && let Some(ty) = read.signature(name)
+                {
+                    println!("[type] {} : {}", name, ty.abbreviated(read.aliases()));
+                    return Ok(());
+                }
+                let ty = types::infer(&lambda_term, read.definitions())?;
+                drop(read);
+                println!("[type] {} : {}", lambda_term.styled(settings.style), ty);
+            }
+            Self::Show {
+                lambda_term,
+                format,
+            } => match format {
+                TermFormat::DeBruijn => {
+                    println!("[{}] {}", format, DeBruijnTerm::from(&lambda_term));
+                }
+            },
+            Self::Inspect { lambda_term } => {
+                let names = |names: std::collections::HashSet<String>| {
+                    let mut names: Vec<_> = names.into_iter().collect();
+                    names.sort();
+                    if names.is_empty() {
+                        "none".to_string()
+                    } else {
+                        names.join(", ")
+                    }
+                };
+                println!("[inspect] {}", lambda_term.styled(settings.style));
+                println!("  form            : {}", lambda_term.form());
+                println!("  redexes         : {}", lambda_term.redex_count());
+                println!("  size            : {}", lambda_term.size());
+                println!("  depth           : {}", lambda_term.depth());
+                println!(
+                    "  free variables  : {}",
+                    names(lambda_term.free_variables())
+                );
+                println!(
+                    "  bound variables : {}",
+                    names(lambda_term.bound_variables())
+                );
+            }
+            Self::Import { .. } => {}
+            Self::Open { module, names } => save_lambda_term
+                .write()
+                .expect("the saved lambda terms lock is poisoned")
+                .import(&module, names.as_deref())?,
+            Self::TypeAlias {
+                name,
+                ty: (ty, span),
+            } => {
+                let mut write = save_lambda_term
+                    .write()
+                    .expect("the saved lambda terms lock is poisoned");
+                let ty = ty.expand_aliases(write.aliases()).map_err(|variable| {
+                    systemf::TypeError::UnboundTypeVariable {
+                        name: variable,
+                        span,
+                    }
+                })?;
+                write.define_alias(name, ty);
             }
-            Instruction::Eval(lambda_term) => {
-                let read = save_lambda_term.read().unwrap();
-                println!("{}", lambda_term.beta_reduction(read.clone()));
+            Self::Annotated { terms, instruction } => {
+                let checked = {
+                    let read = save_lambda_term
+                        .read()
+                        .expect("the saved lambda terms lock is poisoned");
+                    terms
+                        .into_iter()
+                        .map(|term| {
+                            let term = read.resolve_typed(term)?;
+                            let ty = systemf::check(&term, &read)?;
+                            Ok((term, ty))
+                        })
+                        .collect::<Result<Vec<_>, EvalError>>()?
+                };
+                let untyped = Settings {
+                    typed: false,
+                    ..settings.clone()
+                };
+                match *instruction {
+                    Self::Type { .. } => {
+                        let aliases = save_lambda_term
+                            .read()
+                            .expect("the saved lambda terms lock is poisoned")
+                            .aliases()
+                            .clone();
+                        for ((term, _), ty) in checked {
+                            println!("[type] {} : {}", term, ty.abbreviated(&aliases));
+                        }
+                    }
+                    Self::Let {
+                        ref name,
+                        ref module,
+                        ..
+                    } => {
+                        let name = module
+                            .as_ref()
+                            .map_or_else(|| name.clone(), |module| format!("{}.{}", module, name));
+                        Box::pin(instruction.compute(save_lambda_term, &untyped)).await?;
+                        let mut write = save_lambda_term
+                            .write()
+                            .expect("the saved lambda terms lock is poisoned");
+                        for (_, ty) in checked {
+                            write.sign(name.clone(), ty);
+                        }
+                    }
+                    instruction => {
+                        Box::pin(instruction.compute(save_lambda_term, &untyped)).await?
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reduce the lambda term as `LambdaTerm::normalize` does, printing
+    /// every intermediate term in the given style with its contracted redex
+    /// underlined.
+    fn trace_reduction(
+        lambda_term: LambdaTerm,
+        (strategy, target): (Strategy, Target),
+        max_steps: usize,
+        save_lambda_term: &HashMap<String, LambdaTerm>,
+        style: Style,
+    ) -> Result<LambdaTerm, EvalError> {
+        println!(
+            "[{}] trace of {}",
+            reduction_label(strategy, target),
+            lambda_term.styled(style)
+        );
+        let mut step_count = 0;
+        let result = lambda_term.normalize_with(
+            strategy,
+            target,
+            max_steps,
+            save_lambda_term,
+            |term, step| {
+                let (text, mark) = print_marked(term, style, &step.redex);
+                println!("{:>4}. {}", step_count, text);
+                println!(
+                    "      {}{} {}",
+                    " ".repeat(mark.start),
+                    "^".repeat(mark.len()),
+                    step.kind
+                );
+                step_count += 1;
+            },
+        )?;
+        println!("{:>4}. {}", step_count, result.styled(style));
+        Ok(result)
+    }
+
+    /// Reduce the lambda term to the target normal form with the backend of
+    /// the settings.
+    /// Returns the normal form, with a description of the work done if it was
+    /// reached by a machine. The machines follow no strategy and make no eta
+    /// steps on their own, so choosing a strategy or the eta target with them
+    /// is an error.
+    fn evaluate(
+        lambda_term: LambdaTerm,
+        (strategy, target): (Option<Strategy>, Target),
+        max_steps: usize,
+        settings: &Settings,
+        save_lambda_term: &HashMap<String, LambdaTerm>,
+    ) -> Result<(LambdaTerm, Option<String>), EvalError> {
+        if settings.backend != Backend::Substitution {
+            let option = match (strategy, target) {
+                (Some(strategy), _) => Some(format!("the {} strategy", strategy)),
+                (None, Target::Eta) => Some("η-steps only".to_string()),
+                (None, _) => None,
+            };
+            if let Some(option) = option {
+                return Err(EvalError::UnsupportedByBackend {
+                    backend: settings.backend,
+                    option,
+                });
             }
         }
+        let (result, work) = match settings.backend {
+            Backend::Lazy => {
+                let (result, sharing) = lazy::normalize(&lambda_term, max_steps, save_lambda_term)?;
+                (result, sharing.to_string())
+            }
+            Backend::Krivine => {
+                let (result, steps) =
+                    krivine::normalize(&lambda_term, max_steps, save_lambda_term)?;
+                let plural = if steps == 1 { "" } else { "s" };
+                (result, format!("{} step{}", steps, plural))
+            }
+            Backend::Substitution => {
+                return lambda_term
+                    .normalize(
+                        strategy.unwrap_or(settings.strategy),
+                        target,
+                        max_steps,
+                        save_lambda_term,
+                    )
+                    .map(|result| (result, None));
+            }
+        };
+        let result = match target {
+            Target::BetaEta => {
+                result.normalize(settings.strategy, Target::Eta, max_steps, save_lambda_term)?
+            }
+            _ => result,
+        };
+        Ok((result, Some(work)))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::{environment, parse_term};
+
+    /// Reduce a term to the normal form of the strategy.
+    fn normalize(
+        term: LambdaTerm,
+        strategy: Strategy,
+        env: &HashMap<String, LambdaTerm>,
+    ) -> LambdaTerm {
+        term.beta_reduction(strategy, 1000, env)
+            .expect("the term should have a normal form")
+    }
 
     #[test]
     fn test_free_variables() {
@@ -332,4 +1991,486 @@ mod tests {
         let fresh = LambdaTerm::generate_fresh_variable("x", &used_vars, &replacement);
         assert_eq!(fresh, "x3");
     }
+
+    #[test]
+    fn test_strategies_on_discarded_divergent_argument() {
+        // ((k a) omega) has a normal form, but only if omega is never reduced
+        let env = environment(&[("k", "\\x.\\y.x"), ("omega", "((\\s.(s s)) \\s.(s s))")]);
+        let term = parse_term("((k a) omega)");
+        let expected = LambdaTerm::Variable("a".to_string());
+
+        for strategy in [Strategy::NormalOrder, Strategy::CallByName, Strategy::Head] {
+            assert_eq!(normalize(term.clone(), strategy, &env), expected);
+        }
+
+        // Applicative strategies reduce omega first and never reach `a`
+        let mut current = term;
+        for _ in 0..100 {
+            (current, _) = current
+                .step(Strategy::CallByValue, &env)
+                .expect("call-by-value should keep reducing omega");
+        }
+    }
+
+    #[test]
+    fn test_eta_reduction() {
+        let env = environment(&[("id", "\\x.x")]);
+        let reduce = |term: &str, target| {
+            parse_term(term)
+                .normalize(Strategy::NormalOrder, target, 100, &env)
+                .expect("the term has a normal form")
+        };
+
+        assert_eq!(reduce("\\x.(f x)", Target::BetaEta), parse_term("f"));
+        assert_eq!(reduce("\\x.(f x)", Target::Beta), parse_term("\\x.(f x)"));
+        // `x` is free in the function, so this is not an eta redex
+        assert_eq!(
+            reduce("\\x.((f x) x)", Target::BetaEta),
+            parse_term("\\x.((f x) x)")
+        );
+        // Eta steps can appear under lambdas and in arguments, and chain
+        assert_eq!(
+            reduce("\\x.\\y.((f x) y)", Target::BetaEta),
+            parse_term("f")
+        );
+        assert_eq!(reduce("(g \\y.(h y))", Target::Eta), parse_term("(g h)"));
+
+        // The eta target does not unfold names nor contract beta redexes
+        assert_eq!(reduce("\\x.(id x)", Target::Eta), parse_term("id"));
+        assert_eq!(reduce("((\\x.(f x)) a)", Target::Eta), parse_term("(f a)"));
+        assert_eq!(
+            reduce("((\\y.\\x.(y x)) a)", Target::Eta),
+            parse_term("((\\y.y) a)")
+        );
+        // Beta steps come first, and can create eta redexes
+        assert_eq!(
+            reduce("\\x.((\\y.(f y)) x)", Target::BetaEta),
+            parse_term("f")
+        );
+
+        let (step, kind) = parse_term("(a \\x.(f x))")
+            .eta_step()
+            .map(|(term, step)| (term, step.redex))
+            .expect("eta redex");
+        assert_eq!(step, parse_term("(a f)"));
+        assert_eq!(kind, vec![Direction::Argument]);
+    }
+
+    #[test]
+    fn test_inspection() {
+        let forms = [
+            ("\\x.(x ((\\y.y) z))", Form::HeadNormal),
+            ("\\x.((\\y.y) x)", Form::WeakHeadNormal),
+            ("(((\\x.x) y) z)", Form::Reducible),
+            ("(x \\x.x)", Form::Normal),
+        ];
+        for (term, form) in forms {
+            assert_eq!(parse_term(term).form(), form, "form of {}", term);
+        }
+
+        let term = parse_term("(x \\x.((\\y.y) ((\\z.z) x)))");
+        assert_eq!(term.redex_count(), 2);
+        assert_eq!(term.size(), 10);
+        assert_eq!(term.depth(), 6);
+        assert_eq!(term.free_variables(), HashSet::from(["x".to_string()]));
+        assert_eq!(
+            term.bound_variables(),
+            HashSet::from(["x".to_string(), "y".to_string(), "z".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_eta_expansion() {
+        assert_eq!(parse_term("f").eta_expansion(), parse_term("\\x.(f x)"));
+        // The parameter does not capture a free variable of the term
+        let expanded = parse_term("(x x1)").eta_expansion();
+        assert_eq!(expanded, parse_term("\\x2.((x x1) x2)"));
+        assert_eq!(
+            expanded.eta_step().map(|(term, _)| term),
+            Some(parse_term("(x x1)"))
+        );
+    }
+
+    #[test]
+    fn test_strategies_stop_at_their_normal_forms() {
+        let env: HashMap<String, LambdaTerm> = HashMap::new();
+        let term = parse_term("((\\x.\\y.(y x)) ((\\z.z) a))");
+
+        // Full normal form
+        assert_eq!(
+            normalize(term.clone(), Strategy::NormalOrder, &env),
+            parse_term("\\y.(y a)")
+        );
+        assert_eq!(
+            normalize(term.clone(), Strategy::ApplicativeOrder, &env),
+            parse_term("\\y.(y a)")
+        );
+
+        // Head normal form: the argument of the head variable is left alone
+        assert_eq!(
+            normalize(term.clone(), Strategy::Head, &env),
+            parse_term("\\y.(y ((\\z.z) a))")
+        );
+
+        // Weak normal forms: nothing is reduced under the lambda, but
+        // call-by-value reduces the argument before substituting it
+        assert_eq!(
+            normalize(term.clone(), Strategy::CallByName, &env),
+            parse_term("\\y.(y ((\\z.z) a))")
+        );
+        assert_eq!(
+            normalize(term, Strategy::CallByValue, &env),
+            parse_term("\\y.(y a)")
+        );
+    }
+
+    #[test]
+    fn test_strategy_from_str() {
+        for (name, strategy) in Strategy::ALL {
+            assert_eq!(name.parse::<Strategy>(), Ok(strategy));
+        }
+        assert!("lazy".parse::<Strategy>().is_err());
+    }
+
+    #[test]
+    fn test_step_limit() {
+        let env: HashMap<String, LambdaTerm> = HashMap::new();
+        // This term grows at each step, so it never enters a cycle
+        let growing = parse_term("((\\s.((s s) s)) \\s.((s s) s))");
+        assert_eq!(
+            growing.beta_reduction(Strategy::NormalOrder, 50, &env),
+            Err(EvalError::StepLimit { max_steps: 50 })
+        );
+
+        // A term reaching its normal form in exactly the budget succeeds
+        let term = parse_term("((\\x.x) ((\\y.y) z))");
+        assert_eq!(
+            term.clone().beta_reduction(Strategy::NormalOrder, 2, &env),
+            Ok(LambdaTerm::Variable("z".to_string()))
+        );
+        assert_eq!(
+            term.beta_reduction(Strategy::NormalOrder, 1, &env),
+            Err(EvalError::StepLimit { max_steps: 1 })
+        );
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        // The interpreter reduces terms on the main thread, whose stack is
+        // larger than the one of the test threads
+        let result = std::thread::Builder::new()
+            .stack_size(8 << 20)
+            .spawn(|| {
+                // This term grows deeper at each step, and would exhaust the
+                // stack long before the step limit
+                let growing = parse_term(r"(\x. x x x) (\x. x x x)");
+                growing.beta_reduction(Strategy::NormalOrder, 10_000, &HashMap::new())
+            })
+            .expect("the thread should start")
+            .join()
+            .expect("the reduction should not panic");
+        assert!(matches!(
+            result,
+            Err(EvalError::DepthLimit { max_depth: MAX_DEPTH, steps }) if steps < 10_000
+        ));
+    }
+
+    #[test]
+    fn test_backend_options() {
+        let env = HashMap::new();
+        let term = parse_term(r"(\x. x) y");
+        for backend in [Backend::Lazy, Backend::Krivine] {
+            let settings = Settings {
+                strategy: Strategy::NormalOrder,
+                max_steps: 100,
+                trace: false,
+                style: Style::Unicode,
+                view: View::Raw,
+                typed: false,
+                target: Target::Beta,
+                backend,
+            };
+            let evaluate = |options| {
+                Instruction::evaluate(term.clone(), options, 100, &settings, &env)
+                    .map(|(result, _)| result)
+            };
+            assert_eq!(evaluate((None, Target::Beta)), Ok(parse_term("y")));
+            assert_eq!(evaluate((None, Target::BetaEta)), Ok(parse_term("y")));
+
+            // The machines follow no strategy, and do not make eta steps alone
+            assert_eq!(
+                evaluate((Some(Strategy::CallByValue), Target::Beta)),
+                Err(EvalError::UnsupportedByBackend {
+                    backend,
+                    option: "the call-by-value strategy".to_string()
+                })
+            );
+            assert_eq!(
+                evaluate((None, Target::Eta)),
+                Err(EvalError::UnsupportedByBackend {
+                    backend,
+                    option: "η-steps only".to_string()
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_de_bruijn_of_alpha_equivalent_terms() {
+        let term = parse_term("\\x.\\y.((x y) z)");
+        assert_eq!(
+            DeBruijnTerm::from(&term),
+            DeBruijnTerm::from(&parse_term("\\a.\\b.((a b) z)"))
+        );
+        assert_ne!(
+            DeBruijnTerm::from(&term),
+            DeBruijnTerm::from(&parse_term("\\y.\\x.((x y) z)"))
+        );
+    }
+
+    #[test]
+    fn test_cycle_detection() {
+        // ((λs.(s s)) λs.(s s)) reduces to itself
+        let env: HashMap<String, LambdaTerm> = HashMap::new();
+        let omega = parse_term("((\\s.(s s)) \\t.(t t))");
+        match omega.beta_reduction(Strategy::NormalOrder, 1000, &env) {
+            Err(EvalError::Cycle { terms }) => assert_eq!(terms.len(), 2),
+            result => panic!("Expected a cycle, got {:?}", result),
+        }
+
+        // (self self) alternates between two shapes, by unfolding `self`
+        let env = environment(&[("self", "\\s.(s s)")]);
+        let term = parse_term("(self self)");
+        match term
+            .clone()
+            .beta_reduction(Strategy::NormalOrder, 1000, &env)
+        {
+            Err(EvalError::Cycle { terms }) => {
+                assert_eq!(terms.len(), 3);
+                assert_eq!(terms.first(), Some(&term));
+                assert_eq!(terms.last(), Some(&term));
+            }
+            result => panic!("Expected a cycle, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_reduction_steps_are_described() {
+        let env = environment(&[("id", "\\x.x")]);
+        let term = parse_term("\\y.(y (id y))");
+
+        let mut steps = Vec::new();
+        let result = term
+            .normalize_with(
+                Strategy::NormalOrder,
+                Target::Beta,
+                10,
+                &env,
+                |term, step| {
+                    steps.push((
+                        print_marked(term, Style::Unicode, &step.redex),
+                        step.kind.clone(),
+                    ));
+                },
+            )
+            .expect("the term should have a normal form");
+
+        assert_eq!(result, parse_term("\\y.(y y)"));
+        assert_eq!(
+            steps,
+            vec![
+                (
+                    ("λy.(y (id y))".to_string(), 7..9),
+                    StepKind::Unfold("id".to_string())
+                ),
+                (("λy.(y ((λx.x) y))".to_string(), 6..16), StepKind::Beta),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_steps() {
+        let env = environment(&[("id", "\\x.x")]);
+        let term = parse_term("(id (id z))");
+
+        let (result, step_count) = term
+            .clone()
+            .steps(Strategy::NormalOrder, Target::Beta, 2, &env);
+        assert_eq!(result, parse_term("(id z)"));
+        assert_eq!(step_count, 2);
+
+        let (result, step_count) = term.steps(Strategy::NormalOrder, Target::Beta, 10, &env);
+        assert_eq!(result, parse_term("z"));
+        assert_eq!(step_count, 4);
+    }
+
+    #[test]
+    fn test_shadowing_let_name_as_parameter() {
+        // let x = foo; eval \x. x
+        let env = environment(&[("x", "foo")]);
+        let term = parse_term("\\x.x");
+        for (_, strategy) in Strategy::ALL {
+            assert_eq!(normalize(term.clone(), strategy, &env), term);
+        }
+    }
+
+    #[test]
+    fn test_shadowing_let_name_applied() {
+        // The parameter `y` shadows the global `y` inside the lambda only
+        let env = environment(&[("y", "a")]);
+        let term = parse_term("((\\y.(y b)) y)");
+        for (_, strategy) in Strategy::ALL {
+            assert_eq!(normalize(term.clone(), strategy, &env), parse_term("(a b)"));
+        }
+    }
+
+    #[test]
+    fn test_shadowing_let_function_as_parameter() {
+        // let k = \x.\y.x; eval \k. (k a)
+        let env = environment(&[("k", "\\x.\\y.x")]);
+        let term = parse_term("\\k.(k a)");
+        assert_eq!(normalize(term.clone(), Strategy::NormalOrder, &env), term);
+
+        // The bound `k` is substituted, the global one is unfolded
+        let term = parse_term("((\\k.((k a) b)) \\x.\\y.y)");
+        assert_eq!(
+            normalize(term, Strategy::NormalOrder, &env),
+            parse_term("b")
+        );
+        let term = parse_term("((k a) b)");
+        assert_eq!(
+            normalize(term, Strategy::NormalOrder, &env),
+            parse_term("a")
+        );
+    }
+
+    #[test]
+    fn test_shadowing_unfolded_definition_is_not_captured() {
+        // let x = foo; let f = \y. x; eval \x. (f x)
+        // The `x` of `f` is the global one, not the parameter of the lambda.
+        let env = environment(&[("x", "foo"), ("f", "\\y.x")]);
+        let term = parse_term("\\x.(f x)");
+        for strategy in [
+            Strategy::NormalOrder,
+            Strategy::ApplicativeOrder,
+            Strategy::Head,
+        ] {
+            assert_eq!(
+                DeBruijnTerm::from(&normalize(term.clone(), strategy, &env)),
+                DeBruijnTerm::from(&parse_term("\\z.foo"))
+            );
+        }
+    }
+
+    #[test]
+    fn test_shadowing_nested_lambdas() {
+        // let x = foo; eval \x. \y. ((\x. x) y)
+        let env = environment(&[("x", "foo")]);
+        let term = parse_term("\\x.\\y.((\\x.x) y)");
+        assert_eq!(
+            normalize(term, Strategy::NormalOrder, &env),
+            parse_term("\\x.\\y.y")
+        );
+
+        // Only the free occurrence is unfolded
+        let term = parse_term("(x \\x.x)");
+        assert_eq!(
+            normalize(term, Strategy::NormalOrder, &env),
+            parse_term("(foo \\x.x)")
+        );
+    }
+
+    #[test]
+    fn test_de_bruijn_conversion() {
+        // λx.λy.((x y) z) in the context [z] -> λ.λ.((1 0) 2)
+        let term = parse_term("\\x.\\y.((x y) z)");
+        let context = vec!["z".to_string()];
+        let nameless = DeBruijnTerm::from_lambda_term(&term, &context);
+        assert_eq!(nameless.to_string(), "λ.λ.((1 0) 2)");
+        assert_eq!(DeBruijnTerm::from(&term).to_string(), "λ.λ.((1 0) z)");
+
+        // Converting back gives an alpha-equivalent term
+        let back = nameless
+            .to_lambda_term(&context)
+            .expect("every index is in the context");
+        assert_eq!(DeBruijnTerm::from_lambda_term(&back, &context), nameless);
+        assert_eq!(back, parse_term("\\x.\\y.((x y) z)"));
+
+        // An index outside of the context cannot be named
+        assert_eq!(nameless.to_lambda_term(&[]), None);
+    }
+
+    #[test]
+    fn test_de_bruijn_names_avoid_free_variables() {
+        let term = DeBruijnTerm::from(&parse_term("\\a.(a x)"));
+        assert_eq!(term.to_lambda_term(&[]), Some(parse_term("\\y.(y x)")));
+    }
+
+    #[test]
+    fn test_de_bruijn_shift() {
+        // Only the indices pointing outside of the term are shifted
+        // (λy.(x y) z) in the context [x, z] -> (λ.(1 0) 1), then (λ.(3 0) 3)
+        let context = vec!["x".to_string(), "z".to_string()];
+        let term = DeBruijnTerm::from_lambda_term(&parse_term(r"(\y. x y) z"), &context);
+        assert_eq!(term.to_string(), "(λ.(1 0) 1)");
+        assert_eq!(term.shift(2, 0).to_string(), "(λ.(3 0) 3)");
+        assert_eq!(term.shift(2, 0).shift(-2, 0), term);
+
+        // Indices under the cutoff are bound inside the term
+        assert_eq!(term.shift(1, 1).to_string(), "(λ.(1 0) 2)");
+    }
+
+    #[test]
+    fn test_alpha_eq() {
+        assert!(parse_term("\\x.x").alpha_eq(&parse_term("\\y.y")));
+        assert!(parse_term("\\x.\\y.(x z)").alpha_eq(&parse_term("\\a.\\b.(a z)")));
+        assert!(!parse_term("\\x.\\y.x").alpha_eq(&parse_term("\\x.\\y.y")));
+
+        // Free variables are compared by name
+        assert!(!parse_term("\\x.y").alpha_eq(&parse_term("\\x.z")));
+        assert!(!parse_term("\\x.y").alpha_eq(&parse_term("\\y.y")));
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let sources = [
+            "x",
+            "λx.x",
+            "\\été.λα.(α été)",
+            "((λx.(x x)) λx.(x x))",
+            "((f λx.x) (g h))",
+            "λx.λy.((x y) λz.(z x))",
+        ];
+        for source in sources {
+            let term = parse_term(source);
+            assert_eq!(parse_term(&term.to_string()), term, "{}", source);
+        }
+
+        // Names made up by the alpha conversion are printed back as valid
+        // identifiers
+        let term = normalize(
+            parse_term("((\\x.\\y.(x y)) y)"),
+            Strategy::NormalOrder,
+            &HashMap::new(),
+        );
+        assert!(!term.alpha_eq(&parse_term("\\y.(y y)")));
+        assert_eq!(parse_term(&term.to_string()), term);
+    }
+
+    #[test]
+    fn test_fixpoint() {
+        // let rec sum = \n. iszero n 0 (plus n (sum (pred n)))
+        let mut env = environment(&[
+            ("true", "\\x y. x"),
+            ("false", "\\x y. y"),
+            ("iszero", "\\n. n (\\x. false) true"),
+            ("plus", "\\m n f x. m f (n f x)"),
+            ("pred", "\\n f x. n (\\g h. h (g f)) (\\u. x) (\\u. u)"),
+        ]);
+        let body = parse_term("\\n. iszero n 0 (plus n (sum (pred n)))");
+        env.insert("sum".to_string(), LambdaTerm::fixpoint("sum", body));
+
+        let result = normalize(parse_term("sum 3"), Strategy::NormalOrder, &env);
+        assert!(result.alpha_eq(&parse_term("6")), "{}", result);
+    }
 }
@@ -0,0 +1,270 @@
+//! Encodings of data as lambda terms: Church numerals, booleans and pairs,
+//! and Scott lists, along with the decoding of the terms encoding them.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::algo::{DeBruijnTerm, LambdaTerm};
+use crate::printer::Style;
+
+/// The largest number literal, whose Church numeral is deep enough to be
+/// printed, converted and reduced without exhausting the stack.
+pub const MAX_NUMERAL: usize = 1000;
+
+/// The Church numeral of a natural number: `λf.λx.f (f … (f x))`, with `n`
+/// applications of `f`.
+pub fn numeral(n: usize) -> LambdaTerm {
+    let body = (0..n).fold(LambdaTerm::Variable("x".to_string()), |acc, _| {
+        LambdaTerm::Application(
+            Box::new(LambdaTerm::Variable("f".to_string())),
+            Box::new(acc),
+        )
+    });
+    LambdaTerm::LambdaAbstraction(
+        "f".to_string(),
+        Box::new(LambdaTerm::LambdaAbstraction(
+            "x".to_string(),
+            Box::new(body),
+        )),
+    )
+}
+
+/// The values encoded by the term, written as strings. Some terms encode
+/// several values, as `λx.λy.y` which is both `0` and `false`.
+/// Returns an empty vector if the term encodes no known value.
+pub fn decode(term: &LambdaTerm, style: Style) -> Vec<String> {
+    decodings(&DeBruijnTerm::from(term), style)
+}
+
+/// The values encoded by a term in the De Bruijn representation, in the
+/// order: numeral, boolean, pair, list.
+fn decodings(term: &DeBruijnTerm, style: Style) -> Vec<String> {
+    [
+        decode_numeral(term).map(|n| n.to_string()),
+        decode_boolean(term).map(|b| b.to_string()),
+        decode_pair(term, style),
+        decode_list(term, style),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Write a component of a pair or a list: the first value it encodes, or the
+/// term itself if it encodes none.
+fn component(term: &DeBruijnTerm, style: Style) -> String {
+    decodings(term, style)
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| {
+            term.to_lambda_term(&[])
+                .map_or_else(|| term.to_string(), |t| t.styled(style).to_string())
+        })
+}
+
+/// The number `n` of a Church numeral `λf.λx.f (f … (f x))`.
+fn decode_numeral(term: &DeBruijnTerm) -> Option<usize> {
+    let mut body = abstraction_body(abstraction_body(term)?)?;
+    let mut n = 0;
+    while let DeBruijnTerm::Application(func, arg) = body {
+        if **func != DeBruijnTerm::Bound(1) {
+            return None;
+        }
+        body = arg;
+        n += 1;
+    }
+    (*body == DeBruijnTerm::Bound(0)).then_some(n)
+}
+
+/// The boolean of a Church boolean, `λx.λy.x` for true and `λx.λy.y` for
+/// false.
+fn decode_boolean(term: &DeBruijnTerm) -> Option<bool> {
+    match abstraction_body(abstraction_body(term)?)? {
+        DeBruijnTerm::Bound(1) => Some(true),
+        DeBruijnTerm::Bound(0) => Some(false),
+        _ => None,
+    }
+}
+
+/// The pair `(a, b)` of a Church pair `λs.s a b`, written with its
+/// components.
+fn decode_pair(term: &DeBruijnTerm, style: Style) -> Option<String> {
+    let [first, second] = selector_arguments(abstraction_body(term)?, 0)?;
+    Some(format!(
+        "({}, {})",
+        component(&first.shift(-1, 0), style),
+        component(&second.shift(-1, 0), style)
+    ))
+}
+
+/// The list `[a, b, …]` of a Scott list, built from `nil = λn.λc.n` and
+/// `cons h t = λn.λc.c h t`, written with its elements.
+fn decode_list(term: &DeBruijnTerm, style: Style) -> Option<String> {
+    let mut elements = Vec::new();
+    let mut current = term.clone();
+    loop {
+        let body = abstraction_body(abstraction_body(&current)?)?;
+        if *body == DeBruijnTerm::Bound(1) {
+            return Some(format!("[{}]", elements.join(", ")));
+        }
+        let [head, tail] = selector_arguments(body, 1)?;
+        elements.push(component(&head.shift(-2, 0), style));
+        current = tail.shift(-2, 0);
+    }
+}
+
+/// The body of a lambda abstraction.
+const fn abstraction_body(term: &DeBruijnTerm) -> Option<&DeBruijnTerm> {
+    match term {
+        DeBruijnTerm::Abstraction(body) => Some(body),
+        _ => None,
+    }
+}
+
+/// The arguments `a` and `b` of a body `s a b` applying the bound variable of
+/// index 0 to them, where neither argument refers to the bound variables of
+/// indices `0` to `max_index`.
+fn selector_arguments(body: &DeBruijnTerm, max_index: usize) -> Option<[&DeBruijnTerm; 2]> {
+    let DeBruijnTerm::Application(func, second) = body else {
+        return None;
+    };
+    let DeBruijnTerm::Application(selector, first) = func.as_ref() else {
+        return None;
+    };
+    let independent = |term: &DeBruijnTerm| (0..=max_index).all(|index| !refers_to(term, index));
+    (**selector == DeBruijnTerm::Bound(0) && independent(first) && independent(second))
+        .then_some([first, second])
+}
+
+/// Whether the term refers to the bound variable of the given index, as seen
+/// from outside of the term.
+fn refers_to(term: &DeBruijnTerm, index: usize) -> bool {
+    match term {
+        DeBruijnTerm::Bound(i) => *i == index,
+        DeBruijnTerm::Free(_) => false,
+        DeBruijnTerm::Abstraction(body) => refers_to(body, index + 1),
+        DeBruijnTerm::Application(func, arg) => refers_to(func, index) || refers_to(arg, index),
+    }
+}
+
+/// How the results of evaluations are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum View {
+    /// The term only.
+    Raw,
+
+    /// The values the term encodes, or the term if it encodes none.
+    Decoded,
+
+    /// The term, followed by the values it encodes if any, as in
+    /// `λf.λx.(f (f x))  -- 2`.
+    #[default]
+    Both,
+}
+
+impl View {
+    /// All the views, with the name used to select them.
+    pub const ALL: [(&str, Self); 3] = [
+        ("raw", Self::Raw),
+        ("decoded", Self::Decoded),
+        ("both", Self::Both),
+    ];
+
+    /// Write the term in the given style, with the values it encodes as the
+    /// view asks for.
+    pub fn show(self, term: &LambdaTerm, style: Style) -> String {
+        let raw = term.styled(style).to_string();
+        let values = match self {
+            Self::Raw => return raw,
+            Self::Decoded | Self::Both => decode(term, style),
+        };
+        match (self, values.is_empty()) {
+            (_, true) => raw,
+            (Self::Decoded, false) => values.join(" / "),
+            (_, false) => format!("{}  -- {}", raw, values.join(" / ")),
+        }
+    }
+}
+
+impl FromStr for View {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, view)| *view)
+            .ok_or_else(|| {
+                let names = Self::ALL.map(|(name, _)| name).join(", ");
+                format!("unknown view '{}', expected one of: {}", s, names)
+            })
+    }
+}
+
+impl Display for View {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Raw => write!(f, "raw"),
+            Self::Decoded => write!(f, "decoded"),
+            Self::Both => write!(f, "both"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::parse_term;
+
+    #[test]
+    fn test_numeral_literals() {
+        assert_eq!(parse_term("0"), parse_term("\\f x. x"));
+        assert_eq!(parse_term("3"), parse_term("\\f x. f (f (f x))"));
+        for n in [0, 1, 7, 42] {
+            assert_eq!(decode_numeral(&DeBruijnTerm::from(&numeral(n))), Some(n));
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        let cases = [
+            ("\\g y. g (g y)", vec!["2"]),
+            ("\\a b. b", vec!["0", "false"]),
+            ("\\a b. a", vec!["true", "[]"]),
+            ("\\s. s 1 (\\x y. x)", vec!["(1, true)"]),
+            ("\\s. s a (\\x. x)", vec!["(a, λx.x)"]),
+            ("\\n c. c 1 (\\n c. c 2 (\\n c. n))", vec!["[1, 2]"]),
+            // The selector must not appear inside the components
+            ("\\s. s s s", vec![]),
+            ("\\f x. f (x x)", vec![]),
+            ("\\n c. c 1 c", vec![]),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(
+                decode(&parse_term(source), Style::Unicode),
+                expected,
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_views() {
+        let two = parse_term("2");
+        assert_eq!(View::Raw.show(&two, Style::Unicode), "λf.λx.(f (f x))");
+        assert_eq!(View::Decoded.show(&two, Style::Unicode), "2");
+        assert_eq!(
+            View::Both.show(&two, Style::Unicode),
+            "λf.λx.(f (f x))  -- 2"
+        );
+        assert_eq!(
+            View::Both.show(&parse_term("\\a b. b"), Style::Compact),
+            "λa.λb.b  -- 0 / false"
+        );
+
+        // Terms encoding nothing are shown as they are
+        let term = parse_term("f x");
+        assert_eq!(View::Decoded.show(&term, Style::Unicode), "(f x)");
+    }
+}
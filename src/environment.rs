@@ -0,0 +1,275 @@
+//! The names bound by a program, and the modules it imports.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::algo::{EvalError, LambdaTerm};
+use crate::parser::Spanned;
+use crate::systemf::{self, TypedTerm};
+use crate::types;
+
+/// The names bound by `let` instructions, the ones of modules being qualified
+/// as in `Bool.and`, along with the unqualified names made usable by
+/// `import Module` instructions.
+///
+/// The System F types of the annotated definitions and the type aliases are
+/// kept along with them.
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    /// The lambda terms bound to names.
+    definitions: HashMap<String, LambdaTerm>,
+
+    /// The qualified names each imported unqualified name may refer to.
+    imports: HashMap<String, BTreeSet<String>>,
+
+    /// The System F types of the names bound to annotated terms.
+    signatures: HashMap<String, systemf::Type>,
+
+    /// The System F types named by `type Name = T` instructions.
+    aliases: HashMap<String, systemf::Type>,
+}
+
+impl Environment {
+    /// An environment binding no name.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The lambda terms bound to names, unfolded by the reductions.
+    pub const fn definitions(&self) -> &HashMap<String, LambdaTerm> {
+        &self.definitions
+    }
+
+    /// Bind a name to a lambda term, replacing its previous definition and
+    /// forgetting its System F type.
+    pub fn define(&mut self, name: String, lambda_term: LambdaTerm) {
+        self.signatures.remove(&name);
+        self.definitions.insert(name, lambda_term);
+    }
+
+    /// The System F type of a name bound to an annotated term.
+    pub fn signature(&self, name: &str) -> Option<&systemf::Type> {
+        self.signatures.get(name)
+    }
+
+    /// Record the System F type of a name, once its annotated definition is
+    /// type-checked.
+    pub fn sign(&mut self, name: String, ty: systemf::Type) {
+        self.signatures.insert(name, ty);
+    }
+
+    /// The System F types named by type aliases.
+    pub const fn aliases(&self) -> &HashMap<String, systemf::Type> {
+        &self.aliases
+    }
+
+    /// Name a System F type, replacing the previous alias of that name.
+    pub fn define_alias(&mut self, name: String, ty: systemf::Type) {
+        self.aliases.insert(name, ty);
+    }
+
+    /// The type of every definition, in the alphabetical order of the names:
+    /// the System F type of the annotated ones, written with the type
+    /// aliases, and the type scheme of the others, if they have one.
+    pub fn types(&self) -> Vec<(&str, Option<String>)> {
+        let mut types: Vec<_> = self
+            .definitions
+            .iter()
+            .map(|(name, lambda_term)| {
+                let ty = self.signatures.get(name).map_or_else(
+                    || {
+                        types::scheme(lambda_term, &self.definitions)
+                            .ok()
+                            .map(|scheme| scheme.to_string())
+                    },
+                    |ty| Some(ty.abbreviated(&self.aliases).to_string()),
+                );
+                (name.as_str(), ty)
+            })
+            .collect();
+        types.sort_by_key(|(name, _)| *name);
+        types
+    }
+
+    /// Remove every definition, import and type alias.
+    pub fn clear(&mut self) {
+        self.definitions.clear();
+        self.imports.clear();
+        self.signatures.clear();
+        self.aliases.clear();
+    }
+
+    /// The names defined in a module, without its name, in alphabetical order.
+    fn module_names(&self, module: &str) -> BTreeSet<&str> {
+        self.definitions
+            .keys()
+            .filter_map(|name| name.strip_prefix(module)?.strip_prefix('.'))
+            .collect()
+    }
+
+    /// Make the given names of a module usable unqualified, or all of them if
+    /// none is given.
+    /// Fails if the module is not defined, or does not define one of the
+    /// names.
+    pub fn import(&mut self, module: &str, names: Option<&[String]>) -> Result<(), EvalError> {
+        let defined = self.module_names(module);
+        if defined.is_empty() {
+            return Err(EvalError::UnknownModule {
+                module: module.to_string(),
+            });
+        }
+        let imported: Vec<String> = match names {
+            None => defined.iter().map(|name| name.to_string()).collect(),
+            Some(names) => {
+                if let Some(missing) = names.iter().find(|name| !defined.contains(name.as_str())) {
+                    return Err(EvalError::NotInModule {
+                        module: module.to_string(),
+                        name: missing.clone(),
+                        defined: defined.iter().map(|name| name.to_string()).collect(),
+                    });
+                }
+                names.to_vec()
+            }
+        };
+        for name in imported {
+            let qualified = format!("{}.{}", module, name);
+            self.imports.entry(name).or_default().insert(qualified);
+        }
+        Ok(())
+    }
+
+    /// The qualified names the free names of a term that are imported from a
+    /// module stand for. A name bound outside of any module takes precedence
+    /// over the imported ones.
+    /// Fails if a name is imported from several modules.
+    fn resolutions(
+        &self,
+        free_variables: HashSet<String>,
+    ) -> Result<Vec<(String, String)>, EvalError> {
+        let mut free_variables: Vec<_> = free_variables.into_iter().collect();
+        free_variables.sort();
+        let mut resolutions = Vec::new();
+        for name in free_variables {
+            if self.definitions.contains_key(&name) {
+                continue;
+            }
+            let Some(candidates) = self.imports.get(&name) else {
+                continue;
+            };
+            match candidates.iter().collect::<Vec<_>>().as_slice() {
+                [qualified] => resolutions.push((name, qualified.to_string())),
+                _ => {
+                    return Err(EvalError::AmbiguousName {
+                        name,
+                        candidates: candidates.iter().cloned().collect(),
+                    });
+                }
+            }
+        }
+        Ok(resolutions)
+    }
+
+    /// Replace the free names of the term that are imported from a module by
+    /// their qualified names. A name bound outside of any module takes
+    /// precedence over the imported ones.
+    /// Fails if a name is imported from several modules.
+    pub fn resolve(&self, lambda_term: LambdaTerm) -> Result<LambdaTerm, EvalError> {
+        let resolutions = self.resolutions(lambda_term.free_variables())?;
+        Ok(resolutions
+            .iter()
+            .fold(lambda_term, |resolved, (name, qualified)| {
+                resolved.rename_free_variable(name, qualified)
+            }))
+    }
+
+    /// Replace the free names of the typed term that are imported from a
+    /// module by their qualified names, as `resolve` does.
+    pub fn resolve_typed(
+        &self,
+        (typed_term, span): Spanned<TypedTerm>,
+    ) -> Result<Spanned<TypedTerm>, EvalError> {
+        let resolutions = self.resolutions(typed_term.free_variables())?;
+        let resolved = resolutions
+            .iter()
+            .fold(typed_term, |resolved, (name, qualified)| {
+                resolved.rename_free_variable(name, qualified)
+            });
+        Ok((resolved, span))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A variable of the given name.
+    fn var(name: &str) -> LambdaTerm {
+        LambdaTerm::Variable(name.to_string())
+    }
+
+    /// An environment with the modules `Bool` and `Logic`, both defining
+    /// `true`.
+    fn environment() -> Environment {
+        let mut env = Environment::new();
+        for name in ["Bool.true", "Bool.and", "Logic.true", "id"] {
+            env.define(name.to_string(), var("x"));
+        }
+        env
+    }
+
+    #[test]
+    fn test_selective_import() {
+        let mut env = environment();
+        env.import("Bool", Some(&["and".to_string()]))
+            .expect("Bool defines and");
+        let term = LambdaTerm::Application(Box::new(var("and")), Box::new(var("true")));
+        assert_eq!(
+            env.resolve(term),
+            Ok(LambdaTerm::Application(
+                Box::new(var("Bool.and")),
+                Box::new(var("true"))
+            ))
+        );
+
+        // Bound names are left as they are
+        let term = LambdaTerm::LambdaAbstraction("and".to_string(), Box::new(var("and")));
+        assert_eq!(env.resolve(term.clone()), Ok(term));
+    }
+
+    #[test]
+    fn test_ambiguous_import() {
+        let mut env = environment();
+        env.import("Bool", None).expect("Bool is defined");
+        env.import("Logic", None).expect("Logic is defined");
+        assert_eq!(
+            env.resolve(var("true")),
+            Err(EvalError::AmbiguousName {
+                name: "true".to_string(),
+                candidates: vec!["Bool.true".to_string(), "Logic.true".to_string()],
+            })
+        );
+        assert_eq!(env.resolve(var("Logic.true")), Ok(var("Logic.true")));
+
+        // A definition outside of any module takes precedence
+        env.define("true".to_string(), var("y"));
+        assert_eq!(env.resolve(var("true")), Ok(var("true")));
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let mut env = environment();
+        assert_eq!(
+            env.import("Number", None),
+            Err(EvalError::UnknownModule {
+                module: "Number".to_string()
+            })
+        );
+        assert_eq!(
+            env.import("Bool", Some(&["or".to_string()])),
+            Err(EvalError::NotInModule {
+                module: "Bool".to_string(),
+                name: "or".to_string(),
+                defined: vec!["and".to_string(), "true".to_string()],
+            })
+        );
+    }
+}
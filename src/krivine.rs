@@ -0,0 +1,311 @@
+//! The Krivine machine, reducing terms to their beta normal form by
+//! call-by-name evaluation with closures and environments.
+//!
+//! The machine runs on a term, the environment binding its variables to
+//! closures, and the stack of the arguments it is applied to: arguments are
+//! pushed as closures instead of being substituted. Once the machine stops on
+//! a lambda without argument or on a variable, the result is read back to its
+//! full normal form, by running the machine again on the bodies of the lambdas
+//! and on the arguments of the variables.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::algo::{EvalError, LambdaTerm};
+use crate::lazy::{Code, Normal};
+
+/// What the variables of a term are bound to.
+#[derive(Debug, Clone)]
+enum Binding {
+    /// A term, with the environment of its free variables.
+    Closure(Rc<Code>, Env),
+
+    /// The parameter of a lambda the read back went under, represented by the
+    /// number of lambdas enclosing it.
+    Level(usize),
+}
+
+/// The bindings of the variables of a term, as a linked list from the
+/// parameter of the innermost lambda to the outermost one.
+#[derive(Debug, Clone, Default)]
+struct Env(Option<Rc<(Binding, Self)>>);
+
+impl Env {
+    /// The environment binding the innermost variable.
+    fn push(&self, binding: Binding) -> Self {
+        Self(Some(Rc::new((binding, self.clone()))))
+    }
+
+    /// The binding of the variable of the given index.
+    fn get(&self, index: usize) -> Binding {
+        let mut env = self;
+        for _ in 0..index {
+            env = &env
+                .0
+                .as_ref()
+                .expect("bound variables are in the environment")
+                .1;
+        }
+        env.0
+            .as_ref()
+            .expect("bound variables are in the environment")
+            .0
+            .clone()
+    }
+}
+
+/// The variable a stopped machine is stuck on.
+enum Head {
+    /// The parameter of a lambda the read back went under.
+    Level(usize),
+
+    /// A free variable, not bound by a `let` instruction.
+    Free(String),
+}
+
+/// The state of a machine that cannot run further: a weak head normal form.
+enum Stopped {
+    /// A lambda abstraction without argument to apply it to.
+    Lambda {
+        /// The parameter of the lambda.
+        param: Rc<str>,
+
+        /// The body of the lambda.
+        body: Rc<Code>,
+
+        /// The bindings of the other variables of the body.
+        env: Env,
+    },
+
+    /// A variable, applied to the arguments of the stack, the first argument
+    /// being on top.
+    Stuck(Head, Vec<(Rc<Code>, Env)>),
+}
+
+/// The Krivine machine, with the definitions it unfolds.
+struct Machine<'a> {
+    /// The lambda terms bound to names by `let` instructions.
+    definitions: &'a HashMap<String, LambdaTerm>,
+
+    /// The compiled definitions, compiled once each.
+    compiled: HashMap<String, Rc<Code>>,
+
+    /// The number of beta reductions and unfoldings made.
+    steps: usize,
+
+    /// The maximum number of steps.
+    max_steps: usize,
+}
+
+impl Machine<'_> {
+    /// Count a step.
+    /// Fails if the maximum number of steps is already reached.
+    const fn tick(&mut self) -> Result<(), EvalError> {
+        if self.steps == self.max_steps {
+            return Err(EvalError::StepLimit {
+                max_steps: self.max_steps,
+            });
+        }
+        self.steps += 1;
+        Ok(())
+    }
+
+    /// Run the machine on the code, in the environment and with the stack of
+    /// arguments, until it stops.
+    fn run(
+        &mut self,
+        mut code: Rc<Code>,
+        mut env: Env,
+        mut stack: Vec<(Rc<Code>, Env)>,
+    ) -> Result<Stopped, EvalError> {
+        loop {
+            let current = code.clone();
+            match current.as_ref() {
+                Code::Bound(index) => match env.get(*index) {
+                    Binding::Closure(closure, closure_env) => {
+                        code = closure;
+                        env = closure_env;
+                    }
+                    Binding::Level(level) => return Ok(Stopped::Stuck(Head::Level(level), stack)),
+                },
+                Code::Free(name) => {
+                    let Some(definition) = self.definitions.get(name) else {
+                        return Ok(Stopped::Stuck(Head::Free(name.clone()), stack));
+                    };
+                    self.tick()?;
+                    code = self
+                        .compiled
+                        .entry(name.clone())
+                        .or_insert_with(|| Code::compile(definition, &mut Vec::new()))
+                        .clone();
+                    env = Env::default();
+                }
+                Code::Abstraction(param, body) => {
+                    let Some((arg, arg_env)) = stack.pop() else {
+                        return Ok(Stopped::Lambda {
+                            param: param.clone(),
+                            body: body.clone(),
+                            env,
+                        });
+                    };
+                    self.tick()?;
+                    env = env.push(Binding::Closure(arg, arg_env));
+                    code = body.clone();
+                }
+                Code::Application(func, arg) => {
+                    stack.push((arg.clone(), env.clone()));
+                    code = func.clone();
+                }
+            }
+        }
+    }
+
+    /// Read a stopped machine back to its normal form, `depth` being the
+    /// number of lambdas the read back went under.
+    /// The read back keeps its pending work on the heap: a term without normal
+    /// form may be read back forever, until the maximum number of steps.
+    fn read_back(&mut self, stopped: Stopped, depth: usize) -> Result<Normal, EvalError> {
+        let mut tasks = vec![ReadBack::Stopped(stopped, depth)];
+        let mut normals = Vec::new();
+        while let Some(task) = tasks.pop() {
+            match task {
+                ReadBack::Stopped(Stopped::Lambda { param, body, env }, depth) => {
+                    let body = self.run(body, env.push(Binding::Level(depth)), Vec::new())?;
+                    tasks.push(ReadBack::Abstraction(param));
+                    tasks.push(ReadBack::Stopped(body, depth + 1));
+                }
+                ReadBack::Stopped(Stopped::Stuck(head, stack), depth) => {
+                    normals.push(match head {
+                        Head::Level(level) => Normal::Bound(level),
+                        Head::Free(name) => Normal::Free(name),
+                    });
+                    // The first argument is on top of the stack, so its task
+                    // ends up on top of the tasks
+                    for (arg, arg_env) in stack {
+                        tasks.push(ReadBack::Application);
+                        tasks.push(ReadBack::Argument(arg, arg_env, depth));
+                    }
+                }
+                ReadBack::Argument(arg, arg_env, depth) => {
+                    let arg = self.run(arg, arg_env, Vec::new())?;
+                    tasks.push(ReadBack::Stopped(arg, depth));
+                }
+                ReadBack::Abstraction(param) => {
+                    let body = normals.pop().expect("the body is read back");
+                    normals.push(Normal::Abstraction(param, Box::new(body)));
+                }
+                ReadBack::Application => {
+                    let arg = normals.pop().expect("the argument is read back");
+                    let func = normals.pop().expect("the function is read back");
+                    normals.push(Normal::Application(Box::new(func), Box::new(arg)));
+                }
+            }
+        }
+        Ok(normals.pop().expect("the stopped machine is read back"))
+    }
+}
+
+/// The work left to read a stopped machine back, done from the last task.
+enum ReadBack {
+    /// Read the stopped machine back, under the given number of lambdas.
+    Stopped(Stopped, usize),
+
+    /// Run the machine on an argument, then read it back under the given
+    /// number of lambdas.
+    Argument(Rc<Code>, Env, usize),
+
+    /// Build the lambda abstraction of the given parameter around the last
+    /// normal form read back.
+    Abstraction(Rc<str>),
+
+    /// Apply the normal form read back before the last one to the last one.
+    Application,
+}
+
+/// Reduce the lambda term to its beta normal form with the Krivine machine.
+///
+/// The names bound in `definitions` are unfolded where they are applied or
+/// read back. The normal form is the one normal order reduction reaches, up
+/// to the renaming of bound variables, and the number of steps made is
+/// returned along with it.
+/// Fails if the evaluation takes more than `max_steps` steps.
+pub fn normalize(
+    term: &LambdaTerm,
+    max_steps: usize,
+    definitions: &HashMap<String, LambdaTerm>,
+) -> Result<(LambdaTerm, usize), EvalError> {
+    let mut machine = Machine {
+        definitions,
+        compiled: HashMap::new(),
+        steps: 0,
+        max_steps,
+    };
+    let stopped = machine.run(
+        Code::compile(term, &mut Vec::new()),
+        Env::default(),
+        Vec::new(),
+    )?;
+    let normal = machine.read_back(stopped, 0)?;
+    Ok((normal.named(), machine.steps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::Strategy;
+    use crate::test_support::{environment, parse_term};
+
+    #[test]
+    fn test_same_normal_forms() {
+        let env = environment(&[
+            ("exp", r"\m n. n m"),
+            ("pred", r"\n f x. n (\g h. h (g f)) (\u. x) (\u. u)"),
+        ]);
+        for source in [
+            r"\x. (\y. \x. y) x",
+            r"(\x y. x) a ((\x. x x) (\x. x x))",
+            r"\f. (\x. f (x x)) (\y. y)",
+            r"pred (exp 2 3)",
+        ] {
+            let term = parse_term(source);
+            let (result, _) =
+                normalize(&term, 10_000, &env).expect("the term should have a normal form");
+            let expected = term
+                .beta_reduction(Strategy::NormalOrder, 10_000, &env)
+                .expect("the term should have a normal form");
+            assert!(
+                result.alpha_eq(&expected),
+                "{} gives {} instead of {}",
+                source,
+                result,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_steps() {
+        // Without sharing, the argument is reduced for both occurrences of `x`
+        let term = parse_term(r"(\x. x x) ((\y. y) z)");
+        assert_eq!(
+            normalize(&term, 100, &HashMap::new()),
+            Ok((parse_term("z z"), 3))
+        );
+
+        let term = parse_term(r"(\x. x x) (\x. x x)");
+        assert_eq!(
+            normalize(&term, 100, &HashMap::new()),
+            Err(EvalError::StepLimit { max_steps: 100 })
+        );
+
+        // The normal forms of these mutually recursive definitions are
+        // infinite, and are read back until the maximum number of steps
+        for definition in ["f b", r"\x. f b"] {
+            let env = environment(&[("a", definition), ("b", "a")]);
+            assert_eq!(
+                normalize(&parse_term("a"), 10_000, &env),
+                Err(EvalError::StepLimit { max_steps: 10_000 })
+            );
+        }
+    }
+}
@@ -0,0 +1,593 @@
+//! A call-by-need evaluator, reducing terms to their beta normal form while
+//! sharing the evaluation of the arguments.
+//!
+//! Terms are evaluated in environments binding their variables to thunks: an
+//! argument is only evaluated when its value is needed, and at most once,
+//! however many times it is used. The weak head normal forms found this way
+//! are then read back to the full normal form, by evaluating the bodies of the
+//! lambdas on fresh variables.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::rc::Rc;
+
+use crate::algo::{EvalError, LambdaTerm};
+
+/// A term compiled for the evaluator: bound variables are De Bruijn indices,
+/// and lambdas keep the name of their parameter for the read back.
+#[derive(Debug)]
+pub(crate) enum Code {
+    /// A bound variable, represented by its index. (0 is the parameter of the
+    /// innermost enclosing lambda)
+    Bound(usize),
+
+    /// A free variable, which may be bound by a `let` instruction.
+    Free(String),
+
+    /// A lambda abstraction, represented by its parameter and body.
+    Abstraction(Rc<str>, Rc<Self>),
+
+    /// An application, represented by its function and argument.
+    Application(Rc<Self>, Rc<Self>),
+}
+
+impl Code {
+    /// Compile a lambda term, `names` being the parameters of the enclosing
+    /// lambdas, the innermost one last.
+    pub(crate) fn compile<'a>(term: &'a LambdaTerm, names: &mut Vec<&'a str>) -> Rc<Self> {
+        let code = match term {
+            LambdaTerm::Variable(v) => names.iter().rposition(|name| name == v).map_or_else(
+                || Self::Free(v.clone()),
+                |position| Self::Bound(names.len() - 1 - position),
+            ),
+            LambdaTerm::LambdaAbstraction(param, body) => {
+                names.push(param);
+                let body = Self::compile(body, names);
+                names.pop();
+                Self::Abstraction(param.as_str().into(), body)
+            }
+            LambdaTerm::Application(func, arg) => {
+                Self::Application(Self::compile(func, names), Self::compile(arg, names))
+            }
+        };
+        Rc::new(code)
+    }
+}
+
+/// The thunks bound to the variables of a term, as a linked list from the
+/// parameter of the innermost lambda to the outermost one.
+#[derive(Debug, Clone, Default)]
+struct Env(Option<Rc<(Thunk, Self)>>);
+
+impl Env {
+    /// The environment binding the thunk as the innermost variable.
+    fn push(&self, thunk: Thunk) -> Self {
+        Self(Some(Rc::new((thunk, self.clone()))))
+    }
+
+    /// The thunk bound to the variable of the given index.
+    fn get(&self, index: usize) -> Thunk {
+        let mut env = self;
+        for _ in 0..index {
+            env = &env
+                .0
+                .as_ref()
+                .expect("bound variables are in the environment")
+                .1;
+        }
+        env.0
+            .as_ref()
+            .expect("bound variables are in the environment")
+            .0
+            .clone()
+    }
+}
+
+/// A value, that is, a term in weak head normal form.
+#[derive(Debug, Clone)]
+enum Value {
+    /// A lambda abstraction, with the environment of its free variables.
+    Closure {
+        /// The parameter of the lambda.
+        param: Rc<str>,
+
+        /// The body of the lambda.
+        body: Rc<Code>,
+
+        /// The thunks bound to the other variables of the body.
+        env: Env,
+    },
+
+    /// A term stuck on a variable, which cannot be reduced further at its
+    /// head.
+    Neutral(Rc<Neutral>),
+}
+
+impl Value {
+    /// The addresses of the parts of the value, the same for the copies of a
+    /// value and different for the values alive at the same time otherwise.
+    fn identity(&self) -> (*const (), *const ()) {
+        match self {
+            Self::Closure { body, env, .. } => (
+                Rc::as_ptr(body).cast(),
+                env.0
+                    .as_ref()
+                    .map_or(std::ptr::null(), |env| Rc::as_ptr(env).cast()),
+            ),
+            Self::Neutral(neutral) => (Rc::as_ptr(neutral).cast(), std::ptr::null()),
+        }
+    }
+}
+
+/// A term whose head is a variable.
+#[derive(Debug)]
+enum Neutral {
+    /// The parameter of a lambda the read back went under, represented by the
+    /// number of lambdas enclosing it.
+    Level(usize),
+
+    /// A free variable, not bound by a `let` instruction.
+    Free(String),
+
+    /// The application of a neutral term to an argument.
+    Application(Rc<Self>, Thunk),
+}
+
+/// The state of a thunk, a suspended term which is evaluated at most once.
+#[derive(Debug)]
+enum ThunkState {
+    /// The term is not evaluated yet.
+    Delayed(Rc<Code>, Env),
+
+    /// The term is being evaluated.
+    Forcing,
+
+    /// The term is evaluated.
+    Evaluated {
+        /// The value of the term.
+        value: Value,
+
+        /// The number of steps the evaluation took, steps saved by sharing
+        /// included: the number of steps evaluating it again would take
+        /// without sharing.
+        cost: usize,
+    },
+}
+
+/// A shared suspended term.
+#[derive(Debug, Clone)]
+struct Thunk(Rc<RefCell<ThunkState>>);
+
+impl Thunk {
+    /// A thunk evaluating the code in the environment when it is forced.
+    fn delayed(code: Rc<Code>, env: Env) -> Self {
+        Self(Rc::new(RefCell::new(ThunkState::Delayed(code, env))))
+    }
+
+    /// A thunk that is already evaluated, at no cost.
+    fn evaluated(value: Value) -> Self {
+        Self(Rc::new(RefCell::new(ThunkState::Evaluated {
+            value,
+            cost: 0,
+        })))
+    }
+
+    /// Whether the thunk is evaluated.
+    fn is_evaluated(&self) -> bool {
+        matches!(*self.0.borrow(), ThunkState::Evaluated { .. })
+    }
+}
+
+/// A term in normal form, as read back from a value.
+pub(crate) enum Normal {
+    /// A bound variable, represented by the number of lambdas enclosing its
+    /// binder.
+    Bound(usize),
+
+    /// A free variable.
+    Free(String),
+
+    /// A lambda abstraction, represented by the name its parameter had and its
+    /// body.
+    Abstraction(Rc<str>, Box<Self>),
+
+    /// An application, represented by its function and argument.
+    Application(Box<Self>, Box<Self>),
+}
+
+impl Normal {
+    /// Convert the term to a named lambda term, its lambdas keeping the name
+    /// of their parameter where it captures no variable.
+    pub(crate) fn named(&self) -> LambdaTerm {
+        let mut free = HashSet::new();
+        self.collect_free_names(&mut free);
+        self.to_lambda_term(&mut Vec::new(), &free)
+    }
+
+    /// Add the names of the free variables of the term to `names`.
+    fn collect_free_names(&self, names: &mut HashSet<String>) {
+        match self {
+            Self::Bound(_) => {}
+            Self::Free(name) => {
+                names.insert(name.clone());
+            }
+            Self::Abstraction(_, body) => body.collect_free_names(names),
+            Self::Application(func, arg) => {
+                func.collect_free_names(names);
+                arg.collect_free_names(names);
+            }
+        }
+    }
+
+    /// Whether the bound variable of the given level occurs in the term.
+    fn mentions(&self, level: usize) -> bool {
+        match self {
+            Self::Bound(bound) => *bound == level,
+            Self::Free(_) => false,
+            Self::Abstraction(_, body) => body.mentions(level),
+            Self::Application(func, arg) => func.mentions(level) || arg.mentions(level),
+        }
+    }
+
+    /// Convert the term to a named lambda term, `names` being the parameters
+    /// of the enclosing lambdas, the outermost one first. Lambdas keep the
+    /// name of their parameter, with a numeric suffix if it would capture a
+    /// free variable or a parameter used in their body.
+    fn to_lambda_term(&self, names: &mut Vec<String>, free: &HashSet<String>) -> LambdaTerm {
+        match self {
+            Self::Bound(level) => LambdaTerm::Variable(names[*level].clone()),
+            Self::Free(name) => LambdaTerm::Variable(name.clone()),
+            Self::Abstraction(param, body) => {
+                let captures = |name: &String| {
+                    free.contains(name)
+                        || names
+                            .iter()
+                            .enumerate()
+                            .any(|(level, outer)| outer == name && body.mentions(level))
+                };
+                let param = (0..)
+                    .map(|n: usize| match n {
+                        0 => param.to_string(),
+                        _ => format!("{}{}", param, n),
+                    })
+                    .find(|name| !captures(name))
+                    .unwrap_or_else(|| param.to_string());
+                names.push(param.clone());
+                let body = body.to_lambda_term(names, free);
+                names.pop();
+                LambdaTerm::LambdaAbstraction(param, Box::new(body))
+            }
+            Self::Application(func, arg) => LambdaTerm::Application(
+                Box::new(func.to_lambda_term(names, free)),
+                Box::new(arg.to_lambda_term(names, free)),
+            ),
+        }
+    }
+}
+
+/// The work done by a call-by-need evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sharing {
+    /// The number of beta reductions and unfoldings of `let` names made.
+    pub steps: usize,
+
+    /// The number of steps that sharing saved: the steps the evaluation of
+    /// the shared arguments and names took, each time they were used again.
+    pub saved: usize,
+}
+
+impl Display for Sharing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let plural = if self.steps == 1 { "" } else { "s" };
+        write!(
+            f,
+            "{} step{}, {} saved by sharing",
+            self.steps, plural, self.saved
+        )
+    }
+}
+
+/// The state of a call-by-need evaluation.
+struct Machine<'a> {
+    /// The lambda terms bound to names by `let` instructions.
+    definitions: &'a HashMap<String, LambdaTerm>,
+
+    /// The thunks of the names bound by `let` instructions, shared by all
+    /// their occurrences.
+    globals: HashMap<String, Thunk>,
+
+    /// The names being unfolded, the last one being unfolded to evaluate the
+    /// previous ones.
+    unfolding: Vec<String>,
+
+    /// The identities of the values being read back, the last one being read
+    /// back inside the normal forms of the previous ones.
+    reading: HashSet<(*const (), *const ())>,
+
+    /// The work done so far.
+    sharing: Sharing,
+
+    /// The maximum number of steps.
+    max_steps: usize,
+}
+
+impl Machine<'_> {
+    /// Count a step.
+    /// Fails if the maximum number of steps is already reached.
+    const fn tick(&mut self) -> Result<(), EvalError> {
+        if self.sharing.steps == self.max_steps {
+            return Err(EvalError::StepLimit {
+                max_steps: self.max_steps,
+            });
+        }
+        self.sharing.steps += 1;
+        Ok(())
+    }
+
+    /// The thunk of an argument: the one of the variable for a bound
+    /// variable, so that it is shared, and a new one otherwise.
+    fn delay(code: &Rc<Code>, env: &Env) -> Thunk {
+        match code.as_ref() {
+            Code::Bound(index) => env.get(*index),
+            _ => Thunk::delayed(code.clone(), env.clone()),
+        }
+    }
+
+    /// The value of the thunk, evaluating it if it is not evaluated yet.
+    fn force(&mut self, thunk: &Thunk) -> Result<Value, EvalError> {
+        let state = thunk.0.replace(ThunkState::Forcing);
+        let (value, cost) = match state {
+            ThunkState::Evaluated { value, cost } => {
+                self.sharing.saved += cost;
+                (value, cost)
+            }
+            ThunkState::Delayed(code, env) => {
+                let before = self.sharing.steps + self.sharing.saved;
+                let value = self.eval(code, env)?;
+                (value, self.sharing.steps + self.sharing.saved - before)
+            }
+            // The environment of a thunk is built before the thunk, so it
+            // cannot refer to it
+            ThunkState::Forcing => unreachable!("a thunk does not depend on itself"),
+        };
+        thunk.0.replace(ThunkState::Evaluated {
+            value: value.clone(),
+            cost,
+        });
+        Ok(value)
+    }
+
+    /// The value of a free name: the value of its definition, unfolded once
+    /// and shared by all its occurrences, or the name itself if it has none.
+    /// Fails if the definition needs its own value.
+    fn unfold(&mut self, name: &str) -> Result<Value, EvalError> {
+        let Some(definition) = self.definitions.get(name) else {
+            return Ok(Value::Neutral(Rc::new(Neutral::Free(name.to_string()))));
+        };
+        if let Some(start) = self.unfolding.iter().position(|n| n == name) {
+            let mut terms: Vec<_> = self.unfolding[start..]
+                .iter()
+                .map(|name| LambdaTerm::Variable(name.clone()))
+                .collect();
+            terms.push(LambdaTerm::Variable(name.to_string()));
+            return Err(EvalError::Cycle { terms });
+        }
+        let thunk = self
+            .globals
+            .entry(name.to_string())
+            .or_insert_with(|| {
+                Thunk::delayed(Code::compile(definition, &mut Vec::new()), Env::default())
+            })
+            .clone();
+        // Without sharing, every occurrence would be unfolded
+        if thunk.is_evaluated() {
+            self.sharing.saved += 1;
+        } else {
+            self.tick()?;
+        }
+        self.unfolding.push(name.to_string());
+        let value = self.force(&thunk);
+        self.unfolding.pop();
+        value
+    }
+
+    /// Evaluate the code in the environment to a weak head normal form,
+    /// leaving the arguments unevaluated.
+    fn eval(&mut self, mut code: Rc<Code>, mut env: Env) -> Result<Value, EvalError> {
+        loop {
+            let current = code.clone();
+            match current.as_ref() {
+                Code::Bound(index) => return self.force(&env.get(*index)),
+                Code::Free(name) => return self.unfold(name),
+                Code::Abstraction(param, body) => {
+                    return Ok(Value::Closure {
+                        param: param.clone(),
+                        body: body.clone(),
+                        env,
+                    });
+                }
+                Code::Application(func, arg) => {
+                    let arg = Self::delay(arg, &env);
+                    match self.eval(func.clone(), env)? {
+                        Value::Closure {
+                            body,
+                            env: closure_env,
+                            ..
+                        } => {
+                            self.tick()?;
+                            code = body;
+                            env = closure_env.push(arg);
+                        }
+                        Value::Neutral(neutral) => {
+                            return Ok(Value::Neutral(Rc::new(Neutral::Application(neutral, arg))));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Read a value back to its normal form, `depth` being the number of
+    /// lambdas the read back went under.
+    /// Fails if the value is read back again inside its own normal form.
+    fn read_back(&mut self, value: Value, depth: usize) -> Result<Normal, EvalError> {
+        // Forcing an evaluated thunk takes no step, so the read back of a value
+        // containing itself would never stop, its normal form being infinite
+        let identity = value.identity();
+        if !self.reading.insert(identity) {
+            return Err(EvalError::StepLimit {
+                max_steps: self.max_steps,
+            });
+        }
+        // The value is kept until then, so that its parts are not freed and
+        // their addresses not reused by other values
+        let normal = self.read_back_parts(value.clone(), depth);
+        self.reading.remove(&identity);
+        drop(value);
+        normal
+    }
+
+    /// Read a value back to its normal form as `read_back` does, once it is
+    /// known not to be read back already.
+    fn read_back_parts(&mut self, value: Value, depth: usize) -> Result<Normal, EvalError> {
+        match value {
+            Value::Closure { param, body, env } => {
+                let variable = Value::Neutral(Rc::new(Neutral::Level(depth)));
+                let body = self.eval(body, env.push(Thunk::evaluated(variable)))?;
+                Ok(Normal::Abstraction(
+                    param,
+                    Box::new(self.read_back(body, depth + 1)?),
+                ))
+            }
+            Value::Neutral(neutral) => match neutral.as_ref() {
+                Neutral::Level(level) => Ok(Normal::Bound(*level)),
+                Neutral::Free(name) => Ok(Normal::Free(name.clone())),
+                Neutral::Application(func, arg) => {
+                    let func = self.read_back(Value::Neutral(func.clone()), depth)?;
+                    let arg = self.force(arg)?;
+                    Ok(Normal::Application(
+                        Box::new(func),
+                        Box::new(self.read_back(arg, depth)?),
+                    ))
+                }
+            },
+        }
+    }
+}
+
+/// Reduce the lambda term to its beta normal form by call-by-need evaluation.
+///
+/// The names bound in `definitions` are unfolded where their value is needed.
+/// The normal form is the one normal order reduction reaches, up to the
+/// renaming of bound variables, and the work done is returned along with it.
+/// Fails if the evaluation takes more than `max_steps` steps, if the normal
+/// form is infinite, or if a name needs its own value.
+pub fn normalize(
+    term: &LambdaTerm,
+    max_steps: usize,
+    definitions: &HashMap<String, LambdaTerm>,
+) -> Result<(LambdaTerm, Sharing), EvalError> {
+    let mut machine = Machine {
+        definitions,
+        globals: HashMap::new(),
+        unfolding: Vec::new(),
+        reading: HashSet::new(),
+        sharing: Sharing { steps: 0, saved: 0 },
+        max_steps,
+    };
+    let value = machine.eval(Code::compile(term, &mut Vec::new()), Env::default())?;
+    let normal = machine.read_back(value, 0)?;
+    Ok((normal.named(), machine.sharing))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::Strategy;
+    use crate::test_support::{environment, parse_term};
+
+    #[test]
+    fn test_same_normal_forms() {
+        let env = environment(&[
+            ("mult", r"\m n f. m (n f)"),
+            ("exp", r"\m n. n m"),
+            ("pred", r"\n f x. n (\g h. h (g f)) (\u. x) (\u. u)"),
+            ("k", r"\x y. x"),
+        ]);
+        for source in [
+            r"z",
+            r"\x. (\y. \x. y) x",
+            r"(\x. x x) ((\y. y) z)",
+            r"k a ((\x. x x) (\x. x x))",
+            r"\f. (\x. f (x x)) k",
+            r"pred (exp 2 3)",
+            r"mult 3 (pred 2)",
+        ] {
+            let term = parse_term(source);
+            let (result, _) =
+                normalize(&term, 10_000, &env).expect("the term should have a normal form");
+            let expected = term
+                .beta_reduction(Strategy::NormalOrder, 10_000, &env)
+                .expect("the term should have a normal form");
+            assert!(
+                result.alpha_eq(&expected),
+                "{} gives {} instead of {}",
+                source,
+                result,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_sharing() {
+        // The argument is reduced once, for both occurrences of `x`
+        let term = parse_term(r"(\x. x x) ((\y. y) z)");
+        assert_eq!(
+            normalize(&term, 100, &HashMap::new()),
+            Ok((parse_term("z z"), Sharing { steps: 2, saved: 1 }))
+        );
+
+        // So is a `let` name, unfolded and reduced to a lambda once, the
+        // bodies of lambdas being normalized at each occurrence
+        let env = environment(&[("two", r"(\n f x. f (n f x)) 1")]);
+        let (_, sharing) = normalize(&parse_term(r"\s. s two two"), 100, &env)
+            .expect("the term should have a normal form");
+        assert_eq!(sharing, Sharing { steps: 6, saved: 2 });
+
+        // The names of the parameters are kept unless they capture a variable
+        let term = parse_term(r"\x. (\y. \x. y) x");
+        assert_eq!(
+            normalize(&term, 100, &HashMap::new()).map(|(result, _)| result.to_string()),
+            Ok("λx.λx1.x".to_string())
+        );
+    }
+
+    #[test]
+    fn test_divergence() {
+        let term = parse_term(r"(\x. x x) (\x. x x)");
+        assert_eq!(
+            normalize(&term, 100, &HashMap::new()),
+            Err(EvalError::StepLimit { max_steps: 100 })
+        );
+
+        let env = environment(&[("a", "b"), ("b", "a")]);
+        assert_eq!(
+            normalize(&parse_term("a"), 100, &env),
+            Err(EvalError::Cycle {
+                terms: vec![parse_term("a"), parse_term("b"), parse_term("a")]
+            })
+        );
+
+        // The values of `a` contain themselves once `b` is unfolded, without
+        // taking any step to read them back
+        for definition in ["f b", r"\x. f b"] {
+            let env = environment(&[("a", definition), ("b", "a")]);
+            assert_eq!(
+                normalize(&parse_term("a"), 100, &env),
+                Err(EvalError::StepLimit { max_steps: 100 })
+            );
+        }
+    }
+}
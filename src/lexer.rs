@@ -6,18 +6,24 @@ use std::ops::Range;
 use logos::Logos;
 
 /// Tokens types for the lambda calculus language.
-#[derive(Logos, Debug, PartialEq, Clone)]
-#[logos(skip r"[ \t\f]+")]
+#[derive(Logos, Debug, PartialEq, Eq, Clone)]
+#[logos(skip r"[ \t\r\f]+")]
 #[logos(skip r"//[^\n]*")]
 pub enum TokenType {
     /// The dot character `.`
     #[token(".")]
     Dot,
 
-    /// The lambda character `\`
+    /// The lambda character, written `\` or `λ`
     #[token("\\")]
+    #[token("λ")]
     Lambda,
 
+    /// The type lambda, written `/\` or `Λ`
+    #[token("/\\")]
+    #[token("Λ")]
+    TypeLambda,
+
     /// The left parenthesis `(`
     #[token("(")]
     LParen,
@@ -26,29 +32,124 @@ pub enum TokenType {
     #[token(")")]
     RParen,
 
+    /// The left brace `{`
+    #[token("{")]
+    LBrace,
+
+    /// The right brace `}`
+    #[token("}")]
+    RBrace,
+
+    /// The left bracket `[`
+    #[token("[")]
+    LBracket,
+
+    /// The right bracket `]`
+    #[token("]")]
+    RBracket,
+
+    /// The comma character `,`
+    #[token(",")]
+    Comma,
+
     /// The `let` keyword
     #[token("let")]
     Let,
 
+    /// The `rec` keyword
+    #[token("rec")]
+    Rec,
+
+    /// The `in` keyword
+    #[token("in")]
+    In,
+
+    /// The `where` keyword
+    #[token("where")]
+    Where,
+
     /// The `eval` keyword
     #[token("eval")]
     Eval,
 
+    /// The `trace` keyword
+    #[token("trace")]
+    Trace,
+
+    /// The `step` keyword
+    #[token("step")]
+    Step,
+
+    /// The `steps` keyword
+    #[token("steps")]
+    Steps,
+
+    /// The `assert_eq` keyword
+    #[token("assert_eq")]
+    AssertEq,
+
+    /// The `show` keyword
+    #[token("show")]
+    Show,
+
+    /// The `inspect` keyword
+    #[token("inspect")]
+    Inspect,
+
+    /// The `type` keyword
+    #[token("type")]
+    Type,
+
+    /// The `import` keyword
+    #[token("import")]
+    Import,
+
+    /// The `use` keyword
+    #[token("use")]
+    Use,
+
+    /// The `module` keyword
+    #[token("module")]
+    Module,
+
+    /// The `forall` keyword, also written `∀`
+    #[token("forall")]
+    #[token("∀")]
+    Forall,
+
+    /// The arrow of function types, written `->` or `→`
+    #[token("->")]
+    #[token("→")]
+    Arrow,
+
     /// The equals sign `=`
     #[token("=")]
     Equals,
 
-    /// An identifier (variable name)
-    #[regex("[a-zA-Z_][a-zA-Z0-9_]*", |lex| lex.slice().to_string())]
+    /// The colon character `:`
+    #[token(":")]
+    Colon,
+
+    /// An identifier (variable name), made of Unicode letters, digits and
+    /// underscores, except `λ` and `Λ`
+    #[regex(r"[\p{L}_&&[^λΛ]][\p{L}\p{N}_&&[^λΛ]]*", |lex| lex.slice().to_string())]
     Identifier(String),
 
+    /// A string literal, given without its quotes
+    #[regex(r#""[^"\n]*""#, |lex| lex.slice().trim_matches('"').to_string())]
+    String(String),
+
+    /// A natural number
+    #[regex("[0-9]+", |lex| lex.slice().parse().ok())]
+    Number(usize),
+
     /// A new line character
     #[token("\n")]
     NewLine,
 }
 
 /// A token with its type and span in the source code.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Token {
     /// The type of the token
     pub token_type: TokenType,
@@ -57,18 +158,262 @@ pub struct Token {
     pub span: Range<usize>,
 }
 
+impl TokenType {
+    /// Whether the token is a keyword, spelled as a name but reserved by the
+    /// language.
+    pub const fn is_keyword(&self) -> bool {
+        matches!(
+            self,
+            Self::Let
+                | Self::Rec
+                | Self::In
+                | Self::Where
+                | Self::Eval
+                | Self::Trace
+                | Self::Step
+                | Self::Steps
+                | Self::AssertEq
+                | Self::Show
+                | Self::Inspect
+                | Self::Type
+                | Self::Import
+                | Self::Use
+                | Self::Module
+                | Self::Forall
+        )
+    }
+}
+
 impl Display for TokenType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            TokenType::Dot => write!(f, "dot (.)"),
-            TokenType::Lambda => write!(f, "lambda (\\)"),
-            TokenType::LParen => write!(f, "left parenthesis '('"),
-            TokenType::RParen => write!(f, "right parenthesis ')'"),
-            TokenType::Let => write!(f, "let"),
-            TokenType::Eval => write!(f, "eval"),
-            TokenType::Equals => write!(f, "equals"),
-            TokenType::Identifier(name) => write!(f, "identifier ({})", name),
-            TokenType::NewLine => write!(f, "newline"),
+            Self::Dot => write!(f, "dot (.)"),
+            Self::Lambda => write!(f, "lambda (\\)"),
+            Self::TypeLambda => write!(f, "type lambda (/\\)"),
+            Self::LParen => write!(f, "left parenthesis '('"),
+            Self::RParen => write!(f, "right parenthesis ')'"),
+            Self::LBrace => write!(f, "left brace '{{'"),
+            Self::RBrace => write!(f, "right brace '}}'"),
+            Self::LBracket => write!(f, "left bracket '['"),
+            Self::RBracket => write!(f, "right bracket ']'"),
+            Self::Comma => write!(f, "comma (,)"),
+            Self::Let => write!(f, "let"),
+            Self::Rec => write!(f, "rec"),
+            Self::In => write!(f, "in"),
+            Self::Where => write!(f, "where"),
+            Self::Eval => write!(f, "eval"),
+            Self::Trace => write!(f, "trace"),
+            Self::Step => write!(f, "step"),
+            Self::Steps => write!(f, "steps"),
+            Self::AssertEq => write!(f, "assert_eq"),
+            Self::Show => write!(f, "show"),
+            Self::Inspect => write!(f, "inspect"),
+            Self::Type => write!(f, "type"),
+            Self::Import => write!(f, "import"),
+            Self::Use => write!(f, "use"),
+            Self::Module => write!(f, "module"),
+            Self::Forall => write!(f, "forall"),
+            Self::Arrow => write!(f, "arrow (->)"),
+            Self::Equals => write!(f, "equals"),
+            Self::Colon => write!(f, "colon (:)"),
+            Self::Identifier(name) => write!(f, "identifier ({})", name),
+            Self::String(s) => write!(f, "string (\"{}\")", s),
+            Self::Number(n) => write!(f, "number ({})", n),
+            Self::NewLine => write!(f, "newline"),
+        }
+    }
+}
+
+/// An error found while splitting the source code into tokens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexError {
+    /// The span (start and end indices) of the invalid text in the source code
+    pub span: Range<usize>,
+
+    /// The description of the error
+    pub message: String,
+
+    /// A hint on how to fix the error, if one is known
+    pub hint: Option<&'static str>,
+}
+
+impl LexError {
+    /// Describe the invalid text found at the given span.
+    fn new(text: &str, span: Range<usize>) -> Self {
+        let slice = &text[span.clone()];
+        let (message, hint) = if slice.bytes().all(|b| b.is_ascii_digit()) {
+            (
+                format!("number `{}` is too large", slice),
+                Some("numbers must fit in a machine word"),
+            )
+        } else if slice.starts_with('"') {
+            (
+                "unterminated string".to_string(),
+                Some("strings end with `\"` on the same line"),
+            )
+        } else {
+            let hint = match slice {
+                "#" => Some("comments start with `//`"),
+                ";" => Some("instructions are separated by new lines"),
+                "-" => Some("`->` is only written in types, and numbers are never negative"),
+                "'" => Some("names are made of letters, digits and `_`, as in `x_1`"),
+                ">" => Some("the body of a lambda follows a dot, as in `\\x. x`"),
+                "+" => Some("numbers are added with `plus` from the prelude, as in `plus 1 2`"),
+                "*" => {
+                    Some("numbers are multiplied with `mult` from the prelude, as in `mult 2 3`")
+                }
+                _ => None,
+            };
+            (format!("unexpected character `{}`", slice), hint)
+        };
+        Self {
+            span,
+            message,
+            hint,
+        }
+    }
+}
+
+/// Split the source code into tokens.
+/// Fails with every invalid piece of text if there is any.
+pub fn lex(text: &str) -> Result<Vec<Token>, Vec<LexError>> {
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+
+    for (result_token_type, span) in TokenType::lexer(text).spanned() {
+        match result_token_type {
+            Ok(token_type) => tokens.push(Token { token_type, span }),
+            Err(()) => errors.push(LexError::new(text, span)),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(tokens)
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lex_errors() {
+        let errors =
+            lex("eval λx.$x#\nlet a = 99999999999999999999999").expect_err("invalid source");
+        let found: Vec<_> = errors
+            .iter()
+            .map(|e| (e.span.clone(), e.message.as_str(), e.hint))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (9..10, "unexpected character `$`", None),
+                (
+                    11..12,
+                    "unexpected character `#`",
+                    Some("comments start with `//`")
+                ),
+                (
+                    21..44,
+                    "number `99999999999999999999999` is too large",
+                    Some("numbers must fit in a machine word")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lex_error_hints() {
+        for (source, hint) in [
+            ("eval f ; g", "instructions are separated by new lines"),
+            ("eval \"a", "strings end with `\"` on the same line"),
+            ("eval f -1", "`->` is only written in types"),
+            ("let x' = y", "names are made of letters, digits and `_`"),
+            ("eval \\x => x", "the body of a lambda follows a dot"),
+            ("eval 1 + 2", "numbers are added with `plus`"),
+            ("eval 2 * 3", "numbers are multiplied with `mult`"),
+        ] {
+            let errors = lex(source).expect_err("invalid source");
+            let found = errors[0]
+                .hint
+                .unwrap_or_else(|| panic!("no hint for {}", source));
+            assert!(found.starts_with(hint), "{}: {}", source, found);
         }
     }
+
+    #[test]
+    fn test_lex_valid_source() {
+        let tokens = lex("let id = \\x. x // the identity\r\n").expect("valid source");
+        let token_types: Vec<_> = tokens.into_iter().map(|t| t.token_type).collect();
+        assert_eq!(
+            token_types,
+            vec![
+                TokenType::Let,
+                TokenType::Identifier("id".to_string()),
+                TokenType::Equals,
+                TokenType::Lambda,
+                TokenType::Identifier("x".to_string()),
+                TokenType::Dot,
+                TokenType::Identifier("x".to_string()),
+                TokenType::NewLine,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lex_unicode() {
+        let tokens = lex("λα.(été λ_1.α)").expect("valid source");
+        let found: Vec<_> = tokens.into_iter().map(|t| (t.token_type, t.span)).collect();
+        assert_eq!(
+            found,
+            vec![
+                (TokenType::Lambda, 0..2),
+                (TokenType::Identifier("α".to_string()), 2..4),
+                (TokenType::Dot, 4..5),
+                (TokenType::LParen, 5..6),
+                (TokenType::Identifier("été".to_string()), 6..11),
+                (TokenType::Lambda, 12..14),
+                (TokenType::Identifier("_1".to_string()), 14..16),
+                (TokenType::Dot, 16..17),
+                (TokenType::Identifier("α".to_string()), 17..19),
+                (TokenType::RParen, 19..20),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lex_types() {
+        let ascii: Vec<_> = lex("/\\a. x [forall b. a -> b]")
+            .expect("valid source")
+            .into_iter()
+            .map(|t| t.token_type)
+            .collect();
+        let unicode: Vec<_> = lex("Λa. x [∀b. a → b]")
+            .expect("valid source")
+            .into_iter()
+            .map(|t| t.token_type)
+            .collect();
+        assert_eq!(ascii, unicode);
+        assert_eq!(
+            ascii[..4],
+            [
+                TokenType::TypeLambda,
+                TokenType::Identifier("a".to_string()),
+                TokenType::Dot,
+                TokenType::Identifier("x".to_string()),
+            ]
+        );
+        assert_eq!(ascii[4..6], [TokenType::LBracket, TokenType::Forall]);
+        assert_eq!(
+            ascii[8..],
+            [
+                TokenType::Identifier("a".to_string()),
+                TokenType::Arrow,
+                TokenType::Identifier("b".to_string()),
+                TokenType::RBracket,
+            ]
+        );
+    }
 }
@@ -0,0 +1,17 @@
+//! Lambca (**Lamb**da **ca**lculus), a very tiny DSL to manipulate lambda
+//! calculus terms.
+
+pub mod algo;
+pub mod encoding;
+pub mod environment;
+pub mod krivine;
+pub mod lazy;
+pub mod lexer;
+pub mod parser;
+pub mod prelude;
+pub mod printer;
+pub mod systemf;
+pub mod types;
+
+#[cfg(test)]
+pub(crate) mod test_support;
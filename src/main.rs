@@ -1,132 +1,603 @@
 //! Main program for the lambda calculus interpreter.
 
-use std::collections::HashMap;
+use std::fmt::Display;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::process::exit;
 use std::sync::RwLock;
-use std::{env, fs};
+use std::{fs, io};
 
-use ariadne::{Label, Report, ReportKind, Source};
+use ariadne::{Config, IndexType, Label, Report, ReportKind, Source};
 use chumsky::Parser;
-use chumsky::error::Rich;
-use crate::lexer::TokenType;
-use logos::Logos;
+use chumsky::error::{Rich, RichReason};
+use chumsky::span::SimpleSpan;
+use clap::Parser as _;
 
-mod lexer;
-mod parser;
-mod algo;
+use lambca::algo::{Backend, ImportSource, Instruction, Settings, Strategy, Target};
+use lambca::encoding::View;
+use lambca::environment::Environment;
+use lambca::lexer::{LexError, Token, TokenType};
+use lambca::parser::Spanned;
+use lambca::printer::Style;
+
+mod repl;
+
+/// The configuration of every ariadne report: spans are byte offsets in the
+/// source, as given by the lexer, so that they stay right on Unicode text.
+const REPORT_CONFIG: Config = Config::new().with_index_type(IndexType::Byte);
 
 #[tokio::main]
 async fn main() {
-    let cli_ok = match cli() {
-        Ok(t) => t,
+    let cli = Cli::parse();
+
+    let settings = Settings {
+        strategy: cli.strategy,
+        max_steps: cli.max_steps,
+        trace: cli.trace,
+        style: cli.style,
+        view: cli.view,
+        typed: cli.typed,
+        target: cli.target,
+        backend: cli.backend,
+    };
+
+    let Some(path) = cli.file_path else {
+        repl::run(&settings).await;
+        return;
+    };
+
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
         Err(e) => {
-            eprintln!("{}", e);
+            eprintln!("Could not read file '{}': {}", path, e);
             exit(1);
         }
     };
 
-    let CliResult::Text(text, path) = cli_ok else {
-        println!("Usage: lambca <file_path>");
-        exit(0);
+    let Some(program) = parse_source(&path, &text) else {
+        exit(1);
     };
 
-    let save_lambda_term = RwLock::new(HashMap::new());
+    Report::build(
+        ReportKind::Custom("Info", ariadne::Color::Green),
+        (&path, 0..text.len()),
+    )
+    .with_config(REPORT_CONFIG)
+    .with_message(format!("Successfully parsed '{}'", path))
+    .finish()
+    .print((&path, Source::from(&text)))
+    .expect("failed to print the report");
 
-    let mut tokens = Vec::new();
-    let mut tokens_type = Vec::new();
+    let save_lambda_term = RwLock::new(Environment::new());
+    let mut importing = Vec::new();
+    let succeeded = run_program(
+        program,
+        &path,
+        &text,
+        &save_lambda_term,
+        &settings,
+        &mut importing,
+    )
+    .await;
 
-    for (result_token_type, span) in TokenType::lexer(&text).spanned() {
-        let Ok(token_type) = result_token_type else {
-            continue;
-        };
-        let token = crate::lexer::Token {
-            token_type: token_type.clone(),
-            span,
-        };
-        tokens_type.push(token_type);
-        tokens.push(token);
+    if settings.typed {
+        print_type_summary(
+            &save_lambda_term
+                .read()
+                .expect("the saved lambda terms lock is poisoned"),
+        );
+    }
+    if !succeeded {
+        exit(1);
     }
+}
 
-    let parser = crate::parser::parser();
+/// Print the type of every definition, after a file is run in typed mode.
+fn print_type_summary(save_lambda_term: &Environment) {
+    let types = save_lambda_term.types();
+    if types.is_empty() {
+        return;
+    }
+    println!("Types of the definitions:");
+    for (name, ty) in types {
+        match ty {
+            Some(ty) => println!("  {} : {}", name, ty),
+            None => println!("  {} has no simple type", name),
+        }
+    }
+}
+
+/// A lambca source, split into tokens and parsed into instructions.
+struct Program {
+    /// The tokens of the source.
+    tokens: Vec<Token>,
+
+    /// The instructions of the source, with the span of their tokens.
+    instructions: Vec<Spanned<Instruction>>,
+}
+
+/// Lex and parse a lambca source, reporting the parser errors with ariadne.
+/// Returns `None` if the source could not be parsed.
+fn parse_source(path: &str, text: &str) -> Option<Program> {
+    let tokens = match lambca::lexer::lex(text) {
+        Ok(tokens) => tokens,
+        Err(errors) => {
+            handle_lex_error(errors, path, text);
+            return None;
+        }
+    };
+    let tokens_type: Vec<TokenType> = tokens.iter().map(|t| t.token_type.clone()).collect();
+
+    let parser = lambca::parser::parser();
     let result = parser.parse(tokens_type.as_slice());
 
     match result.into_result() {
-        Ok(instructions) => {
-            Report::build(
-                ReportKind::Custom("Info", ariadne::Color::Green),
-                (&path, 0..text.len()),
-            )
-            .with_message(format!("Successfully parsed '{}'", path))
-            .finish()
-            .print((&path, Source::from(&text)))
-            .unwrap();
+        Ok(instructions) => Some(Program {
+            tokens,
+            instructions,
+        }),
+        Err(errors) => {
+            handle_error(errors, path, text, &tokens);
+            None
+        }
+    }
+}
+
+/// Run the instructions of a program, reporting the evaluation errors with
+/// ariadne and going on with the next instructions, unless an import failed.
+/// `importing` holds the files whose imports are being run, to detect import
+/// cycles.
+/// Returns whether every instruction succeeded.
+async fn run_program(
+    program: Program,
+    path: &str,
+    text: &str,
+    save_lambda_term: &RwLock<Environment>,
+    settings: &Settings,
+    importing: &mut Vec<PathBuf>,
+) -> bool {
+    // The program is one of the files being imported while it runs, unless
+    // it does not come from a file
+    let file = fs::canonicalize(path).ok();
+    importing.extend(file.clone());
+
+    let mut succeeded = true;
+    for (instruction, span) in program.instructions {
+        let span = || source_span(&program.tokens, span);
+        match instruction {
+            Instruction::Import { source } => {
+                let result = run_import(source, path, save_lambda_term, settings, importing).await;
+                if let Err(error) = result {
+                    succeeded = false;
+                    let note = error.note();
+                    report_failure(
+                        path,
+                        text,
+                        span(),
+                        "Import Error",
+                        &error,
+                        error.help(),
+                        note,
+                    );
+                    // The next instructions may use the missing definitions
+                    break;
+                }
+            }
+            instruction => {
+                if let Err(error) = instruction.compute(save_lambda_term, settings).await {
+                    succeeded = false;
+                    let note = error.note(settings.style);
+                    // Type errors of explicitly typed terms point at the subterm
+                    let span = error
+                        .span()
+                        .map_or_else(span, |span| source_span(&program.tokens, span));
+                    report_failure(path, text, span, error.title(), &error, error.help(), note);
+                }
+            }
+        }
+    }
+
+    if file.is_some() {
+        importing.pop();
+    }
+    succeeded
+}
 
-            for instruction in instructions {
-                instruction.compute(&save_lambda_term).await;
+/// Run the program imported by an instruction of the program at `path`, with
+/// the same definitions.
+async fn run_import(
+    source: ImportSource,
+    path: &str,
+    save_lambda_term: &RwLock<Environment>,
+    settings: &Settings,
+    importing: &mut Vec<PathBuf>,
+) -> Result<(), ImportError> {
+    let (name, text) = match source {
+        ImportSource::Prelude => (
+            lambca::prelude::NAME.to_string(),
+            lambca::prelude::SOURCE.to_string(),
+        ),
+        ImportSource::File(file) => {
+            let resolved = Path::new(path)
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .join(&file);
+            let unreadable = |error| ImportError::Unreadable {
+                path: file.clone(),
+                error,
+            };
+            let canonical = fs::canonicalize(&resolved).map_err(unreadable)?;
+            if let Some(start) = importing.iter().position(|p| *p == canonical) {
+                let mut chain = importing[start..].to_vec();
+                chain.push(canonical);
+                return Err(ImportError::Cycle { chain });
             }
+            let text = fs::read_to_string(&resolved).map_err(unreadable)?;
+            (resolved.display().to_string(), text)
         }
-        Err(errors) => handle_error(errors.clone(), &path, &text, &tokens),
     };
+
+    let failed = || ImportError::Failed { name: name.clone() };
+    let program = parse_source(&name, &text).ok_or_else(failed)?;
+    let run = run_program(program, &name, &text, save_lambda_term, settings, importing);
+    if Box::pin(run).await {
+        Ok(())
+    } else {
+        Err(failed())
+    }
+}
+
+/// Why an `import` or `use` instruction failed.
+#[derive(Debug)]
+enum ImportError {
+    /// The imported file could not be read.
+    Unreadable {
+        /// The path of the file, as written in the instruction.
+        path: String,
+
+        /// The error met while reading the file.
+        error: io::Error,
+    },
+
+    /// The imported file is already being imported.
+    Cycle {
+        /// The files importing each other, from the one imported again to
+        /// itself.
+        chain: Vec<PathBuf>,
+    },
+
+    /// The imported program has errors, reported in its own source.
+    Failed {
+        /// The name of the imported program.
+        name: String,
+    },
+}
+
+impl ImportError {
+    /// A hint on how to get rid of the error.
+    const fn help(&self) -> &'static str {
+        match self {
+            Self::Unreadable { .. } => "paths are relative to the directory of the importing file",
+            Self::Cycle { .. } => {
+                "files cannot import each other, move the shared definitions to another file"
+            }
+            Self::Failed { .. } => "the errors are reported in the imported program",
+        }
+    }
+
+    /// Additional details about the error.
+    fn note(&self) -> Option<String> {
+        match self {
+            Self::Cycle { chain } => Some(format!(
+                "the import cycle is:\n  {}",
+                chain
+                    .iter()
+                    .map(|file| file.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n→ ")
+            )),
+            Self::Unreadable { .. } | Self::Failed { .. } => None,
+        }
+    }
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unreadable { path, error } => write!(f, "could not read '{}': {}", path, error),
+            Self::Cycle { chain } => write!(
+                f,
+                "'{}' is already being imported",
+                chain
+                    .last()
+                    .map_or_else(String::new, |file| file.display().to_string())
+            ),
+            Self::Failed { name } => write!(f, "'{}' has errors", name),
+        }
+    }
 }
 
-/// Handle parser errors by reporting them with ariadne and exiting the
-/// program.
-fn handle_error(
-    errors: Vec<Rich<TokenType>>,
-    file_path: &str,
-    source: &str,
-    tokens: &Vec<crate::lexer::Token>,
+/// Report with ariadne the failure of the instruction at the given span.
+fn report_failure(
+    path: &str,
+    text: &str,
+    span: Range<usize>,
+    title: &str,
+    error: &impl Display,
+    help: &str,
+    note: Option<String>,
 ) {
+    let mut report = Report::build(ReportKind::Error, (path, span.clone()))
+        .with_config(REPORT_CONFIG)
+        .with_message(title)
+        .with_label(Label::new((path, span)).with_message(error.to_string()))
+        .with_help(help);
+    if let Some(note) = note {
+        report = report.with_note(note);
+    }
+    report
+        .finish()
+        .print((path, Source::from(text)))
+        .expect("failed to print the report");
+}
+
+/// Convert a span of token indices, as given by the parser, to a span in the
+/// source code.
+fn source_span(tokens: &[Token], span: SimpleSpan) -> Range<usize> {
+    let span_token_type: Range<usize> = span.into_range();
+    tokens.get(span_token_type.start).map_or_else(
+        // We suppose that if there is no the element in the tokens vector, the error
+        // is about a missing token at the end of the vector.
+        || tokens.last().map_or(0..0, |t| t.span.end..(t.span.end)),
+        |t_start| {
+            tokens
+                .get(span_token_type.end - 1)
+                .map_or(t_start.span.start..t_start.span.end, |t_end| {
+                    t_start.span.start..t_end.span.end
+                })
+        },
+    )
+}
+
+/// Handle lexer errors by reporting them with ariadne.
+fn handle_lex_error(errors: Vec<LexError>, file_path: &str, source: &str) {
     for e in errors {
-        let span_token_type: std::ops::Range<usize> = e.span().into_iter();
-        let span: std::ops::Range<usize> = match tokens.get(span_token_type.start) {
-            Some(t_start) => match tokens.get(span_token_type.end - 1) {
-                Some(t_end) => t_start.span.start..t_end.span.end,
-                None => t_start.span.start..t_start.span.end,
-            },
-            // We suppose that if there is no the element in the tokens vector, the error
-            // is about a missing token at the end of the vector.
-            None => tokens
-                .last()
-                .map(|t| t.span.end..(t.span.end))
-                .unwrap_or(0..0),
+        let mut report = Report::build(ReportKind::Error, (file_path, e.span.clone()))
+            .with_config(REPORT_CONFIG)
+            .with_message("Lexer Error")
+            .with_label(Label::new((file_path, e.span)).with_message(e.message));
+        if let Some(hint) = e.hint {
+            report = report.with_help(hint);
+        }
+        report
+            .finish()
+            .print((file_path, Source::from(source)))
+            .expect("failed to print the report");
+    }
+}
+
+/// Handle parser errors by reporting them with ariadne.
+fn handle_error(errors: Vec<Rich<TokenType>>, file_path: &str, source: &str, tokens: &[Token]) {
+    for e in errors {
+        let span = source_span(tokens, *e.span());
+        let message = match e.reason() {
+            RichReason::Custom(message) => message.clone(),
+            _ => format!(
+                "expected {}",
+                e.expected()
+                    .map(|f| f.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
         };
-        Report::build(ReportKind::Error, (file_path, span.clone()))
+        let mut report = Report::build(ReportKind::Error, (file_path, span.clone()))
+            .with_config(REPORT_CONFIG)
             .with_message("Parser Error")
-            .with_label(Label::new((file_path, span)).with_message(format!(
-                "expected {}",
-                e.expected().map(|f| f.to_string()).collect::<Vec<_>>().join(", ")
-            )))
+            .with_label(Label::new((file_path, span)).with_message(message));
+        if let Some(hint) = lambca::parser::keyword_hint(&e) {
+            report = report.with_help(hint);
+        }
+        report
             .finish()
             .print((file_path, Source::from(source)))
-            .unwrap();
+            .expect("failed to print the report");
     }
-    exit(1);
 }
 
-/// The result of the command line interface parsing.
-enum CliResult {
-    /// The text to parse and its file path.
-    Text(String, String),
+/// Lambca, a very tiny DSL to manipulate lambda calculus terms.
+#[derive(clap::Parser)]
+#[command(version, about)]
+struct Cli {
+    /// The lambca file to run. An interactive session is started if no file
+    /// is given.
+    file_path: Option<String>,
+
+    /// The reduction strategy used by `eval` instructions that do not choose
+    /// one: normal, applicative, cbn, cbv or head.
+    #[arg(short, long, default_value = "normal")]
+    strategy: Strategy,
+
+    /// The maximum number of reduction steps of `eval` instructions that do
+    /// not choose one.
+    #[arg(short, long, default_value_t = 10_000)]
+    max_steps: usize,
+
+    /// The normal form reached by `eval` instructions that do not choose
+    /// one: beta, eta (eta steps only) or betaeta.
+    #[arg(long, default_value = "beta")]
+    target: Target,
 
-    /// Display the help message.
-    Help,
+    /// The evaluator of `eval` and `assert_eq` instructions: substitution
+    /// (reducing with the strategy), lazy (call-by-need, sharing the
+    /// evaluation of the arguments) or krivine (the call-by-name Krivine
+    /// machine).
+    #[arg(long, default_value = "substitution")]
+    backend: Backend,
+
+    /// Print every intermediate term of every evaluation, as `trace` does.
+    #[arg(short, long)]
+    trace: bool,
+
+    /// The style terms are printed in: compact (minimal parentheses),
+    /// lambca (the input syntax) or unicode (the input syntax with `λ`).
+    #[arg(long, default_value = "unicode")]
+    style: Style,
+
+    /// How the results of `eval` instructions are shown: raw (the term),
+    /// decoded (the numeral, boolean, pair or list it encodes) or both.
+    #[arg(long, default_value = "both")]
+    view: View,
+
+    /// Type-check the terms of `let` and `eval` instructions before running
+    /// them, and print the types of the definitions once the file is run.
+    #[arg(long)]
+    typed: bool,
 }
 
-/// Parse the command line arguments and return the file path to parse or an
-/// error message.
-fn cli() -> Result<CliResult, String> {
-    let mut args = env::args();
-    let Some(first) = args.nth(1) else {
-        return Err("No arguments provided. \nUsage: lambca <file_path>".to_owned());
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    if first == "-h" || first == "--help" {
-        return Ok(CliResult::Help);
-    } else {
-        let file_path = first;
-        let text = fs::read_to_string(&file_path)
-            .map_err(|_| "Could not read file \nUsage: lambca <file_path>".to_owned())?;
-        return Ok(CliResult::Text(text, file_path));
+    /// The default settings of the command line.
+    fn settings() -> Settings {
+        Settings {
+            strategy: Strategy::NormalOrder,
+            max_steps: 10_000,
+            trace: false,
+            style: Style::default(),
+            view: View::default(),
+            typed: false,
+            target: Target::Beta,
+            backend: Backend::default(),
+        }
+    }
+
+    /// A new directory for the files of a test.
+    fn fixture_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lambca-{}-{}", test, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("failed to create the fixture directory");
+        dir
+    }
+
+    /// Write the files of a test, given by their paths relative to `dir`.
+    fn write_files(dir: &Path, files: &[(&str, &str)]) {
+        for (name, content) in files {
+            let path = dir.join(name);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).expect("failed to create the fixture directory");
+            }
+            fs::write(path, content).expect("failed to write the fixture file");
+        }
+    }
+
+    /// Run the file `main.lc` of the directory, returning whether it succeeded
+    /// and the names it defined.
+    async fn run_main(dir: &Path) -> (bool, Vec<String>) {
+        let path = dir.join("main.lc").display().to_string();
+        let text = fs::read_to_string(&path).expect("failed to read the fixture file");
+        let program = parse_source(&path, &text).expect("the fixture should parse");
+        let save_lambda_term = RwLock::new(Environment::new());
+        let succeeded = run_program(
+            program,
+            &path,
+            &text,
+            &save_lambda_term,
+            &settings(),
+            &mut Vec::new(),
+        )
+        .await;
+        let mut names: Vec<String> = save_lambda_term
+            .read()
+            .expect("the saved lambda terms lock is poisoned")
+            .definitions()
+            .keys()
+            .cloned()
+            .collect();
+        names.sort();
+        (succeeded, names)
+    }
+
+    #[tokio::test]
+    async fn test_import_paths_are_relative_to_the_importing_file() {
+        let dir = fixture_dir("relative");
+        write_files(
+            &dir,
+            &[
+                ("main.lc", "import \"lib/bool.lc\"\nlet main = not true\n"),
+                (
+                    "lib/bool.lc",
+                    "import \"base.lc\"\nlet not = \\b. b false true\n",
+                ),
+                ("lib/base.lc", "let true = \\x y. x\nlet false = \\x y. y\n"),
+            ],
+        );
+        assert_eq!(
+            run_main(&dir).await,
+            (
+                true,
+                vec!["false", "main", "not", "true"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect()
+            )
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_import_cycles() {
+        let dir = fixture_dir("cycle");
+        write_files(
+            &dir,
+            &[
+                ("main.lc", "import \"a.lc\"\nlet after = x\n"),
+                ("a.lc", "import \"b.lc\"\n"),
+                ("b.lc", "import \"a.lc\"\n"),
+            ],
+        );
+        assert_eq!(run_main(&dir).await, (false, Vec::new()));
+
+        // The chain goes from the file imported again to itself
+        let a = fs::canonicalize(dir.join("a.lc")).expect("the file exists");
+        let mut importing = vec![a.clone()];
+        let result = run_import(
+            ImportSource::File("a.lc".to_string()),
+            &dir.join("main.lc").display().to_string(),
+            &RwLock::new(Environment::new()),
+            &settings(),
+            &mut importing,
+        )
+        .await;
+        assert!(matches!(result, Err(ImportError::Cycle { chain }) if chain == vec![a.clone(), a]));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_failed_imports_stop_the_file() {
+        let dir = fixture_dir("failed");
+        write_files(
+            &dir,
+            &[
+                ("main.lc", "import \"bad.lc\"\nlet after = x\n"),
+                ("bad.lc", "let first = y\neval (\n"),
+            ],
+        );
+        // Neither the definitions of a file with errors, nor the instructions
+        // after its import are run
+        assert_eq!(run_main(&dir).await, (false, Vec::new()));
+
+        let result = run_import(
+            ImportSource::File("nowhere.lc".to_string()),
+            &dir.join("main.lc").display().to_string(),
+            &RwLock::new(Environment::new()),
+            &settings(),
+            &mut Vec::new(),
+        )
+        .await;
+        assert!(
+            matches!(result, Err(ImportError::Unreadable { path, .. }) if path == "nowhere.lc")
+        );
+        let _ = fs::remove_dir_all(&dir);
     }
 }
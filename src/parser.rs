@@ -3,67 +3,1054 @@
 use chumsky::error::Rich;
 use chumsky::prelude::*;
 
+use crate::algo::{
+    EvalOptions, ImportSource, Instruction, LambdaTerm, Strategy, Target, TermFormat,
+};
+use crate::encoding::MAX_NUMERAL;
 use crate::lexer::TokenType;
-use crate::algo::{Instruction, LambdaTerm};
+use crate::systemf::{Type, TypedTerm};
+
+/// A value with the span of the tokens it was parsed from.
+pub type Spanned<T> = (T, SimpleSpan);
+
+/// The name bound by a local binding, whether the binding is recursive, and
+/// the type given to the name, as in `let rec f: A = M in N`.
+type Binder = (bool, String, Option<Type>);
+
+/// A single option given to an `eval` instruction, as in `eval:cbv:500`.
+enum EvalOption {
+    /// The reduction strategy to use.
+    Strategy(Strategy),
+
+    /// The maximum number of reduction steps.
+    MaxSteps(usize),
+
+    /// The normal form to reach.
+    Target(Target),
+
+    /// Eta-expand the normal form, written `expand`.
+    Expand,
+}
+
+impl std::str::FromStr for EvalOption {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "expand" {
+            return Ok(Self::Expand);
+        }
+        s.parse()
+            .map(Self::Strategy)
+            .or_else(|_| s.parse().map(Self::Target))
+            .map_err(|_: String| {
+                let names = |names: &[&str]| names.join(", ");
+                format!(
+                    "unknown option '{}', expected a strategy ({}), a target ({}) or expand",
+                    s,
+                    names(&Strategy::ALL.map(|(name, _)| name)),
+                    names(&Target::ALL.map(|(name, _)| name)),
+                )
+            })
+    }
+}
+
+/// An argument a term is applied to.
+enum Argument {
+    /// A term, as in `f x`.
+    Term(Spanned<TypedTerm>),
+
+    /// A type, as in `id [A]`.
+    Type(Type),
+}
+
+/// The term `(\name. body) value`, binding the name to the value in the body,
+/// or to the fixpoint of the value for a recursive binding. The type given to
+/// the name, if any, is the one of the value.
+fn local_binding(
+    (recursive, name, annotation): Binder,
+    value: Spanned<TypedTerm>,
+    body: Spanned<TypedTerm>,
+    span: SimpleSpan,
+) -> Spanned<TypedTerm> {
+    let (value, annotation) = if recursive {
+        (
+            TypedTerm::fixpoint(name.clone(), annotation, value, span),
+            None,
+        )
+    } else {
+        (value, annotation)
+    };
+    let abstraction = (
+        TypedTerm::Abstraction(name, annotation, Box::new(body)),
+        span,
+    );
+    (
+        TypedTerm::Application(Box::new(abstraction), Box::new(value)),
+        span,
+    )
+}
+
+/// The instruction, type-checked in System F before being run if one of its
+/// terms holds types.
+fn annotate(instruction: Instruction, terms: Vec<Spanned<TypedTerm>>) -> Instruction {
+    if terms.iter().any(|(term, _)| term.is_annotated()) {
+        Instruction::Annotated {
+            terms,
+            instruction: Box::new(instruction),
+        }
+    } else {
+        instruction
+    }
+}
+
+/// The name bound by a `let` instruction.
+fn bound_name(instruction: &Instruction) -> Option<&str> {
+    match instruction {
+        Instruction::Let { name, .. } => Some(name),
+        Instruction::Annotated { instruction, .. } => bound_name(instruction),
+        _ => None,
+    }
+}
+
+/// Refer to the names bound in a module by their qualified names in one of
+/// its instructions, as in `Bool.and` for `and` in the module `Bool`.
+/// The name bound by a `let` is left as it is in its own definition, where it
+/// can only refer to itself.
+fn qualify(instruction: Instruction, module: &str, names: &[String]) -> Instruction {
+    let own_name = bound_name(&instruction).map(str::to_string);
+    let qualified_names = || {
+        names
+            .iter()
+            .filter(|name| Some(*name) != own_name.as_ref())
+            .map(|name| (name, format!("{}.{}", module, name)))
+    };
+    let qualify_term = |lambda_term: LambdaTerm| {
+        qualified_names().fold(lambda_term, |lambda_term, (name, qualified)| {
+            lambda_term.rename_free_variable(name, &qualified)
+        })
+    };
+    match instruction {
+        Instruction::Let {
+            name,
+            lambda_term,
+            recursive,
+            ..
+        } => Instruction::Let {
+            lambda_term: qualify_term(lambda_term),
+            name,
+            recursive,
+            module: Some(module.to_string()),
+        },
+        Instruction::Eval {
+            lambda_term,
+            options,
+        } => Instruction::Eval {
+            lambda_term: qualify_term(lambda_term),
+            options,
+        },
+        Instruction::Step {
+            lambda_term,
+            count,
+            strategy,
+            target,
+        } => Instruction::Step {
+            lambda_term: qualify_term(lambda_term),
+            count,
+            strategy,
+            target,
+        },
+        Instruction::AssertEq {
+            left,
+            right,
+            options,
+        } => Instruction::AssertEq {
+            left: qualify_term(left),
+            right: qualify_term(right),
+            options,
+        },
+        Instruction::Type { lambda_term } => Instruction::Type {
+            lambda_term: qualify_term(lambda_term),
+        },
+        Instruction::Show {
+            lambda_term,
+            format,
+        } => Instruction::Show {
+            lambda_term: qualify_term(lambda_term),
+            format,
+        },
+        Instruction::Inspect { lambda_term } => Instruction::Inspect {
+            lambda_term: qualify_term(lambda_term),
+        },
+        Instruction::Annotated { terms, instruction } => Instruction::Annotated {
+            terms: terms
+                .into_iter()
+                .map(|(term, span)| {
+                    let term = qualified_names().fold(term, |term, (name, qualified)| {
+                        term.rename_free_variable(name, &qualified)
+                    });
+                    (term, span)
+                })
+                .collect(),
+            instruction: Box::new(qualify(*instruction, module, names)),
+        },
+        Instruction::Import { .. } | Instruction::Open { .. } | Instruction::TypeAlias { .. } => {
+            instruction
+        }
+    }
+}
+
+/// The labels of the parsers of names, or of terms starting with a name,
+/// which a keyword is mistaken for.
+const NAME_LABELS: [&str; 9] = [
+    "identifier",
+    "lambda term",
+    "application",
+    "variable",
+    "variable identifier",
+    "parameters",
+    "type parameters",
+    "module name",
+    "imported names",
+];
+
+/// A hint for the parser error, when a keyword is found where a name is
+/// expected.
+pub fn keyword_hint(error: &Rich<'_, TokenType>) -> Option<String> {
+    let keyword = error.found().filter(|token| token.is_keyword())?;
+    let expects_name = error
+        .expected()
+        .any(|pattern| NAME_LABELS.contains(&pattern.to_string().as_str()));
+    expects_name.then(|| format!("`{}` is a keyword, and cannot be used as a name", keyword))
+}
 
 /// A parser for the lambda calculus language.
 pub fn parser<'src>()
--> impl Parser<'src, &'src [TokenType], Vec<Instruction>, extra::Err<Rich<'src, TokenType>>> + Clone
-{
+-> impl Parser<'src, &'src [TokenType], Vec<Spanned<Instruction>>, extra::Err<Rich<'src, TokenType>>>
++ Clone {
     let ident = select!(
-        TokenType::Identifier(name) => name.to_owned(),
+        TokenType::Identifier(name) => name,
     )
     .labelled("identifier");
 
+    let number = select!(
+        TokenType::Number(n) => n,
+    )
+    .labelled("number");
+
+    // A number literal stands for its Church numeral
+    let numeral = number
+        .validate(|n, e, emitter| {
+            if n > MAX_NUMERAL {
+                emitter.emit(Rich::custom(
+                    e.span(),
+                    format!("number literals are at most {}", MAX_NUMERAL),
+                ));
+            }
+            n.min(MAX_NUMERAL)
+        })
+        .map_with(|n, e| (TypedTerm::Numeral(n), e.span()))
+        .labelled("number literal");
+
+    // A name bound in a module is referred to as `Module.name`. The qualified
+    // part is looked ahead without being reported as expected, so that a
+    // misplaced token after a name does not call for a dot.
+    let qualification = custom(|input| {
+        let before = input.save();
+        if let (Some(TokenType::Dot), Some(TokenType::Identifier(name))) =
+            (input.next(), input.next())
+        {
+            return Ok(Some(name));
+        }
+        input.rewind(before);
+        Ok(None)
+    });
+    let name = ident
+        .then(qualification)
+        .map(|(first, second)| match second {
+            Some(name) => format!("{}.{}", first, name),
+            None => first,
+        });
+
+    // `A -> B -> C` is `A -> (B -> C)`, and the body of a `forall` extends as
+    // far right as possible
+    let ty = recursive(|ty| {
+        let atom = choice((
+            ident.map(Type::Variable),
+            ty.clone()
+                .delimited_by(just(TokenType::LParen), just(TokenType::RParen)),
+        ));
+
+        let forall = just(TokenType::Forall)
+            .ignore_then(ident.repeated().at_least(1).collect::<Vec<_>>())
+            .then_ignore(just(TokenType::Dot).labelled("dot"))
+            .then(ty.clone())
+            .map(|(vars, body)| {
+                vars.into_iter()
+                    .rev()
+                    .fold(body, |body, var| Type::Forall(var, Box::new(body)))
+            });
+
+        let arrow = atom
+            .then(just(TokenType::Arrow).ignore_then(ty).or_not())
+            .map(|(from, to)| match to {
+                Some(to) => Type::Arrow(Box::new(from), Box::new(to)),
+                None => from,
+            });
+
+        choice((forall, arrow))
+    })
+    .labelled("type");
+
     let lambda_term = recursive(|expr| {
-        let variable = ident
+        let variable = name
             .labelled("variable identifier")
-            .map(LambdaTerm::Variable)
+            .map_with(|name, e| (TypedTerm::Variable(name), e.span()))
             .labelled("variable");
 
+        // A parameter is annotated with its type as in `\x: A. M`
+        let params = choice((
+            ident
+                .map_with(|param, e| (param, e.span()))
+                .then_ignore(just(TokenType::Colon))
+                .then(ty.clone())
+                .map(|((param, span), ty): ((String, SimpleSpan), Type)| {
+                    vec![(param, Some(ty), span)]
+                }),
+            ident
+                .map_with(|param, e| (param, None, e.span()))
+                .repeated()
+                .at_least(1)
+                .collect::<Vec<_>>(),
+        ))
+        .labelled("parameters");
+
+        // `\x y. M` is sugar for `\x.\y. M`, and the body of a lambda extends
+        // as far right as possible. The inner lambdas span from their
+        // parameter to the end of the body.
         let lambda_abs = just(TokenType::Lambda)
             .labelled("lambda")
-            .ignore_then(ident)
-            .labelled("parameter")
+            .ignore_then(params)
             .then_ignore(just(TokenType::Dot).labelled("dot"))
             .then(expr.clone())
-            .map(|(param, body)| LambdaTerm::LambdaAbstraction(param, Box::new(body)))
+            .map_with(|(params, body), e| {
+                let outer: SimpleSpan = e.span();
+                params
+                    .into_iter()
+                    .enumerate()
+                    .rev()
+                    .fold(body, |body, (i, (param, ty, span))| {
+                        let span = if i == 0 {
+                            outer
+                        } else {
+                            SimpleSpan::from(span.start..outer.end)
+                        };
+                        (TypedTerm::Abstraction(param, ty, Box::new(body)), span)
+                    })
+            })
             .labelled("lambda abstraction");
 
-        let application = just(TokenType::LParen)
-            .ignore_then(expr.clone())
-            .then(expr)
-            .then_ignore(just(TokenType::RParen))
-            .map(|(func, arg)| LambdaTerm::Application(func.into(), arg.into()))
+        // `/\a. M` abstracts the term over the type `a`
+        let type_abs = just(TokenType::TypeLambda)
+            .ignore_then(
+                ident
+                    .map_with(|var, e| (var, e.span()))
+                    .repeated()
+                    .at_least(1)
+                    .collect::<Vec<(String, SimpleSpan)>>(),
+            )
+            .labelled("type parameters")
+            .then_ignore(just(TokenType::Dot).labelled("dot"))
+            .then(expr.clone())
+            .map_with(|(vars, body), e| {
+                let outer: SimpleSpan = e.span();
+                vars.into_iter()
+                    .enumerate()
+                    .rev()
+                    .fold(body, |body, (i, (var, span))| {
+                        let span = if i == 0 {
+                            outer
+                        } else {
+                            SimpleSpan::from(span.start..outer.end)
+                        };
+                        (TypedTerm::TypeAbstraction(var, Box::new(body)), span)
+                    })
+            })
+            .labelled("type abstraction");
+
+        let group = expr
+            .clone()
+            .delimited_by(just(TokenType::LParen), just(TokenType::RParen))
+            .labelled("parenthesized term");
+
+        let atom = choice((variable, numeral, group));
+
+        let type_argument = ty
+            .clone()
+            .delimited_by(just(TokenType::LBracket), just(TokenType::RBracket))
+            .labelled("type argument");
+
+        // Application is left-associative: `f a b` is `((f a) b)`, and `f [A] b`
+        // is `((f [A]) b)`. A lambda can only be the last argument, as its body
+        // takes in what follows it.
+        let application = atom
+            .clone()
+            .foldl_with(
+                choice((
+                    atom.map(Argument::Term),
+                    lambda_abs.clone().map(Argument::Term),
+                    type_abs.clone().map(Argument::Term),
+                    type_argument.map(Argument::Type),
+                ))
+                .repeated(),
+                |func, arg, e| {
+                    let term = match arg {
+                        Argument::Term(arg) => TypedTerm::Application(func.into(), arg.into()),
+                        Argument::Type(ty) => TypedTerm::TypeApplication(func.into(), ty),
+                    };
+                    (term, e.span())
+                },
+            )
             .labelled("application");
 
-        choice((lambda_abs, application, variable))
+        let rec_flag = just(TokenType::Rec).or_not().map(|rec| rec.is_some());
+
+        // The name of a local binding can be given a type, as in `let x: A = M`
+        let binder = rec_flag
+            .then(ident)
+            .then(just(TokenType::Colon).ignore_then(ty.clone()).or_not())
+            .map(|((recursive, name), ty)| (recursive, name, ty));
+
+        // `let x = M in N` and `N where x = M` both bind `x` to `M` in `N`
+        let let_in = just(TokenType::Let)
+            .ignore_then(binder.clone())
+            .then_ignore(just(TokenType::Equals))
+            .then(expr.clone())
+            .then_ignore(just(TokenType::In))
+            .then(expr)
+            .map_with(|((binder, value), body), e| local_binding(binder, value, body, e.span()))
+            .labelled("'let ... in' expression");
+
+        let term = choice((lambda_abs, type_abs, let_in, application));
+
+        let where_clause = just(TokenType::Where)
+            .ignore_then(binder)
+            .then_ignore(just(TokenType::Equals))
+            .then(term.clone())
+            .labelled("'where' clause");
+
+        // Each `where` clause scopes over everything before it
+        term.foldl_with(where_clause.repeated(), |body, (binder, value), e| {
+            local_binding(binder, value, body, e.span())
+        })
     })
     .labelled("lambda term");
 
+    // A term that ends where it starts to be followed by another one.
+    let atom_term = choice((
+        name.map_with(|name, e| (TypedTerm::Variable(name), e.span())),
+        numeral,
+        lambda_term
+            .clone()
+            .delimited_by(just(TokenType::LParen), just(TokenType::RParen)),
+    ))
+    .labelled("variable or parenthesized term");
+
     let let_term = just(TokenType::Let)
-        .ignore_then(ident)
+        .ignore_then(just(TokenType::Rec).or_not().map(|rec| rec.is_some()))
+        .then(ident)
         .then_ignore(just(TokenType::Equals))
         .then(lambda_term.clone())
-        .map(|(name, body)| Instruction::Let {
-            name,
-            lambda_term: body,
+        .validate(|((recursive, name), body), e, emitter| {
+            if !recursive && body.0.free_variables().contains(&name) {
+                emitter.emit(Rich::custom(
+                    e.span(),
+                    format!(
+                        "`{}` refers to itself, use `let rec` for a recursive definition",
+                        name
+                    ),
+                ));
+            }
+            let instruction = Instruction::Let {
+                name,
+                lambda_term: body.0.erase(),
+                recursive,
+                module: None,
+            };
+            annotate(instruction, vec![body])
         })
         .labelled("'let' binding");
 
-    let eval_term = just(TokenType::Eval)
+    let eval_option = just(TokenType::Colon)
+        .ignore_then(choice((
+            ident.try_map(|name, span| {
+                name.parse::<EvalOption>()
+                    .map_err(|message| Rich::custom(span, message))
+            }),
+            number.map(EvalOption::MaxSteps),
+        )))
+        .map_with(|option, e| (option, e.span()))
+        .labelled("evaluation option");
+
+    let eval_options = eval_option
+        .repeated()
+        .collect::<Vec<_>>()
+        .try_map(|options, _| {
+            let mut eval_options = EvalOptions::default();
+            for (option, span) in options {
+                let duplicate = match option {
+                    EvalOption::Strategy(strategy) => {
+                        eval_options.strategy.replace(strategy).is_some()
+                    }
+                    EvalOption::MaxSteps(max_steps) => {
+                        eval_options.max_steps.replace(max_steps).is_some()
+                    }
+                    EvalOption::Target(target) => eval_options.target.replace(target).is_some(),
+                    EvalOption::Expand => std::mem::replace(&mut eval_options.expand, true),
+                };
+                if duplicate {
+                    return Err(Rich::custom(span, "this option is given more than once"));
+                }
+            }
+            Ok(eval_options)
+        });
+
+    let eval_term = choice((
+        just(TokenType::Eval).to(false),
+        just(TokenType::Trace).to(true),
+    ))
+    .then(eval_options.clone())
+    .then(lambda_term.clone())
+    .map(|((trace, options), body)| {
+        let instruction = Instruction::Eval {
+            lambda_term: body.0.erase(),
+            options: EvalOptions { trace, ..options },
+        };
+        annotate(instruction, vec![body])
+    })
+    .labelled("'eval' instruction");
+
+    // Only the result of an evaluation is eta-expanded
+    let comparison_options = eval_options.clone().try_map(|options, span| {
+        if options.expand {
+            Err(Rich::custom(
+                span,
+                "only `eval` and `trace` eta-expand their result",
+            ))
+        } else {
+            Ok(options)
+        }
+    });
+
+    let step_options =
+        comparison_options
+            .clone()
+            .try_map(|options, span| match options.max_steps {
+                Some(_) => Err(Rich::custom(
+                    span,
+                    "the number of steps is given after the `steps` keyword",
+                )),
+                None => Ok((options.strategy, options.target)),
+            });
+
+    let step_term = choice((
+        just(TokenType::Step)
+            .ignore_then(step_options.clone())
+            .map(|options| (options, 1)),
+        just(TokenType::Steps)
+            .ignore_then(step_options)
+            .then(number),
+    ))
+    .then(lambda_term.clone())
+    .map(|(((strategy, target), count), body)| {
+        let instruction = Instruction::Step {
+            lambda_term: body.0.erase(),
+            count,
+            strategy,
+            target,
+        };
+        annotate(instruction, vec![body])
+    })
+    .labelled("'step' instruction");
+
+    let assert_eq_term = just(TokenType::AssertEq)
+        .ignore_then(comparison_options)
+        .then(atom_term)
+        .then(lambda_term.clone())
+        .map(|((options, left), right)| {
+            let instruction = Instruction::AssertEq {
+                left: left.0.erase(),
+                right: right.0.erase(),
+                options,
+            };
+            annotate(instruction, vec![left, right])
+        })
+        .labelled("'assert_eq' instruction");
+
+    let show_term = just(TokenType::Show)
+        .ignore_then(ident.try_map(|name, span| {
+            name.parse::<TermFormat>()
+                .map_err(|message| Rich::custom(span, message))
+        }))
+        .then(lambda_term.clone())
+        .map(|(format, body)| {
+            let instruction = Instruction::Show {
+                lambda_term: body.0.erase(),
+                format,
+            };
+            annotate(instruction, vec![body])
+        })
+        .labelled("'show' instruction");
+
+    let inspect_term = just(TokenType::Inspect)
         .ignore_then(lambda_term.clone())
-        .map(|body| Instruction::Eval(body))
-        .labelled("'eval' instruction");
+        .map(|body| {
+            let instruction = Instruction::Inspect {
+                lambda_term: body.0.erase(),
+            };
+            annotate(instruction, vec![body])
+        })
+        .labelled("'inspect' instruction");
 
-    choice((let_term, eval_term))
-        .separated_by(
-            just(TokenType::NewLine)
-                .labelled("newline")
-                .repeated()
-                .at_least(1),
+    let type_term = just(TokenType::Type)
+        .ignore_then(lambda_term.clone())
+        .map(|body| {
+            let instruction = Instruction::Type {
+                lambda_term: body.0.erase(),
+            };
+            annotate(instruction, vec![body])
+        })
+        .labelled("'type' instruction");
+
+    let type_alias = just(TokenType::Type)
+        .ignore_then(ident)
+        .then_ignore(just(TokenType::Equals))
+        .then(ty.map_with(|ty, e| (ty, e.span())))
+        .map(|(name, ty)| Instruction::TypeAlias { name, ty })
+        .labelled("type alias");
+
+    let imported_names = ident
+        .separated_by(just(TokenType::Comma))
+        .at_least(1)
+        .collect::<Vec<_>>()
+        .delimited_by(just(TokenType::LParen), just(TokenType::RParen))
+        .labelled("imported names");
+
+    // `import "file"` runs a file, `import Bool (and, or)` makes names of a
+    // module usable unqualified
+    let import_term = just(TokenType::Import)
+        .ignore_then(choice((
+            select!(TokenType::String(path) => path)
+                .labelled("path")
+                .map(|path| Instruction::Import {
+                    source: ImportSource::File(path),
+                }),
+            ident
+                .labelled("module name")
+                .then(imported_names.or_not())
+                .map(|(module, names)| Instruction::Open { module, names }),
+        )))
+        .labelled("'import' instruction");
+
+    let use_term = just(TokenType::Use)
+        .ignore_then(ident.try_map(|name, span| match name.as_str() {
+            "prelude" => Ok(ImportSource::Prelude),
+            _ => Err(Rich::custom(
+                span,
+                format!("unknown library '{}', expected: prelude", name),
+            )),
+        }))
+        .map(|source| Instruction::Import { source })
+        .labelled("'use' instruction");
+
+    let newlines = just(TokenType::NewLine)
+        .labelled("newline")
+        .repeated()
+        .at_least(1);
+
+    let instruction = choice((
+        let_term,
+        eval_term,
+        step_term,
+        assert_eq_term,
+        type_alias,
+        type_term,
+        show_term,
+        inspect_term,
+    ))
+    .map_with(|instruction, e| (instruction, e.span()));
+
+    // The names bound in a module are qualified by its name, both outside of
+    // the module and inside it
+    let module_block = just(TokenType::Module)
+        .ignore_then(ident.labelled("module name"))
+        .then(
+            instruction
+                .clone()
+                .separated_by(newlines.clone())
+                .allow_leading()
+                .allow_trailing()
+                .collect::<Vec<_>>()
+                .delimited_by(just(TokenType::LBrace), just(TokenType::RBrace)),
         )
-        .allow_leading()
-        .allow_trailing()
-        .collect()
+        .map(|(module, instructions)| {
+            let names: Vec<_> = instructions
+                .iter()
+                .filter_map(|(instruction, _)| bound_name(instruction).map(str::to_string))
+                .collect();
+            instructions
+                .into_iter()
+                .map(|(instruction, span)| (qualify(instruction, &module, &names), span))
+                .collect::<Vec<_>>()
+        })
+        .labelled("module");
+
+    choice((
+        instruction.map(|instruction| vec![instruction]),
+        choice((import_term, use_term)).map_with(|instruction, e| vec![(instruction, e.span())]),
+        module_block,
+    ))
+    .separated_by(newlines)
+    .allow_leading()
+    .allow_trailing()
+    .collect::<Vec<_>>()
+    .map(|items| items.into_iter().flatten().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex;
+    use crate::test_support::parse_term;
+
+    /// Parse a source into instructions, or into the spans and messages of
+    /// the errors (spans of tokens for the parser errors, of bytes for the
+    /// lexer ones).
+    fn parse(source: &str) -> Result<Vec<Instruction>, Vec<(SimpleSpan, String)>> {
+        let tokens: Vec<_> = lex(source)
+            .map_err(|errors| {
+                errors
+                    .into_iter()
+                    .map(|e| (SimpleSpan::from(e.span), e.message))
+                    .collect::<Vec<_>>()
+            })?
+            .into_iter()
+            .map(|token| token.token_type)
+            .collect();
+        parser()
+            .parse(tokens.as_slice())
+            .into_result()
+            .map(|instructions| instructions.into_iter().map(|(i, _)| i).collect())
+            .map_err(|errors| {
+                errors
+                    .into_iter()
+                    .map(|e| (*e.span(), e.reason().to_string()))
+                    .collect()
+            })
+    }
+
+    #[test]
+    fn test_application_is_left_associative() {
+        assert_eq!(parse_term("f a b c"), parse_term("(((f a) b) c)"));
+        assert_eq!(parse_term("f (g a) b"), parse_term("((f (g a)) b)"));
+        assert_eq!(parse_term("((f))"), parse_term("f"));
+    }
+
+    #[test]
+    fn test_lambda_body_extends_to_the_right() {
+        assert_eq!(parse_term("\\x. f x y"), parse_term("\\x.((f x) y)"));
+        assert_eq!(parse_term("f a \\x. x b"), parse_term("((f a) \\x.(x b))"));
+        assert_eq!(parse_term("(\\x. x) y"), parse_term("((\\x.x) y)"));
+    }
+
+    #[test]
+    fn test_multi_parameter_lambda() {
+        assert_eq!(
+            parse_term("\\x y z. x z (y z)"),
+            parse_term("\\x.\\y.\\z.((x z) (y z))")
+        );
+        assert_eq!(parse_term("λf x. f x"), parse_term("\\f.\\x.(f x)"));
+    }
+
+    #[test]
+    fn test_assert_eq_sides() {
+        match parse("assert_eq (f a) g b").as_deref() {
+            Ok([Instruction::AssertEq { left, right, .. }]) => {
+                assert_eq!(*left, parse_term("f a"));
+                assert_eq!(*right, parse_term("g b"));
+            }
+            result => panic!("Expected an assert_eq instruction, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_eval_options() {
+        match parse("eval:cbv:betaeta:expand:50 f").as_deref() {
+            Ok([Instruction::Eval { options, .. }]) => {
+                assert_eq!(options.strategy, Some(Strategy::CallByValue));
+                assert_eq!(options.target, Some(Target::BetaEta));
+                assert_eq!(options.max_steps, Some(50));
+                assert!(options.expand);
+            }
+            result => panic!("Expected an eval instruction, got {:?}", result),
+        }
+        match parse("assert_eq:eta f \\x. f x").as_deref() {
+            Ok([Instruction::AssertEq { options, .. }]) => {
+                assert_eq!(options.target, Some(Target::Eta));
+            }
+            result => panic!("Expected an assert_eq instruction, got {:?}", result),
+        }
+
+        let errors = parse("eval:fast f").expect_err("unknown option");
+        assert_eq!(
+            errors,
+            vec![(
+                SimpleSpan::from(2..3),
+                "unknown option 'fast', expected a strategy (normal, applicative, cbn, cbv, \
+                 head), a target (beta, eta, betaeta) or expand"
+                    .to_string()
+            )]
+        );
+        let errors = parse("step:expand f").expect_err("expanded step");
+        assert_eq!(
+            errors[0].1,
+            "only `eval` and `trace` eta-expand their result"
+        );
+    }
+
+    #[test]
+    fn test_local_bindings() {
+        let expected = parse_term("(\\x. f x) a");
+        assert_eq!(parse_term("let x = a in f x"), expected);
+        assert_eq!(parse_term("f x where x = a"), expected);
+
+        // The value of a binding can itself hold a binding
+        assert_eq!(
+            parse_term("let x = let y = a in y in x"),
+            parse_term("(\\x. x) ((\\y. y) a)")
+        );
+
+        // Later `where` clauses scope over earlier ones
+        assert_eq!(
+            parse_term("f x y where x = y where y = a"),
+            parse_term("(\\y. (\\x. f x y) y) a")
+        );
+
+        // A recursive binding is bound to the fixpoint of its value
+        assert_eq!(
+            parse_term("g 0 where rec g = \\n. g n"),
+            LambdaTerm::Application(
+                Box::new(parse_term("\\g. g 0")),
+                Box::new(LambdaTerm::fixpoint("g", parse_term("\\n. g n")))
+            )
+        );
+    }
+
+    #[test]
+    fn test_recursive_let() {
+        match parse("let rec f = \\x. f x").as_deref() {
+            Ok([Instruction::Let { recursive, .. }]) => assert!(recursive),
+            result => panic!("Expected a let instruction, got {:?}", result),
+        }
+
+        // Without `rec`, the whole definition is reported
+        let errors = parse("eval a\nlet f = \\x. f x").expect_err("self-reference");
+        assert_eq!(
+            errors,
+            vec![(
+                SimpleSpan::from(3..11),
+                "`f` refers to itself, use `let rec` for a recursive definition".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_imports() {
+        match parse("import \"lib/bool.lambca\"\nuse prelude").as_deref() {
+            Ok(
+                [
+                    Instruction::Import { source: file },
+                    Instruction::Import { source: prelude },
+                ],
+            ) => {
+                assert_eq!(*file, ImportSource::File("lib/bool.lambca".to_string()));
+                assert_eq!(*prelude, ImportSource::Prelude);
+            }
+            result => panic!("Expected two import instructions, got {:?}", result),
+        }
+
+        let errors = parse("use stdlib").expect_err("unknown library");
+        assert_eq!(
+            errors,
+            vec![(
+                SimpleSpan::from(1..2),
+                "unknown library 'stdlib', expected: prelude".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_modules() {
+        let source = "module Bool {
+              let true = \\x y. x
+              let rec loop = \\b. loop (not b)
+              let not = \\b. b false true
+            }
+            import Bool (not, true)
+            import Bool";
+        match parse(source).as_deref() {
+            Ok(
+                [
+                    Instruction::Let { name, module, .. },
+                    Instruction::Let {
+                        lambda_term: loop_term,
+                        ..
+                    },
+                    Instruction::Let {
+                        lambda_term: not_term,
+                        ..
+                    },
+                    Instruction::Open {
+                        module: open,
+                        names,
+                    },
+                    Instruction::Open { names: all, .. },
+                ],
+            ) => {
+                assert_eq!((name.as_str(), module.as_deref()), ("true", Some("Bool")));
+                // Names of the module are qualified, except in their own definition
+                assert_eq!(*loop_term, parse_term("\\b. loop (Bool.not b)"));
+                assert_eq!(*not_term, parse_term("\\b. b false Bool.true"));
+                assert_eq!(open, "Bool");
+                assert_eq!(*names, Some(vec!["not".to_string(), "true".to_string()]));
+                assert_eq!(*all, None);
+            }
+            result => panic!("Expected a module and two imports, got {:?}", result),
+        }
+
+        assert_eq!(
+            parse_term("Bool.and a (Bool.not b)"),
+            LambdaTerm::Application(
+                Box::new(LambdaTerm::Application(
+                    Box::new(LambdaTerm::Variable("Bool.and".to_string())),
+                    Box::new(LambdaTerm::Variable("a".to_string()))
+                )),
+                Box::new(LambdaTerm::Application(
+                    Box::new(LambdaTerm::Variable("Bool.not".to_string())),
+                    Box::new(LambdaTerm::Variable("b".to_string()))
+                ))
+            )
+        );
+    }
+
+    #[test]
+    fn test_type_annotations() {
+        let source = "type Nat = forall a. (a -> a) -> a -> a
+            let id = /\\a. \\x: a. x
+            eval id [Nat -> forall b. b] 2";
+        match parse(source).as_deref() {
+            Ok(
+                [
+                    Instruction::TypeAlias { name, ty: (ty, _) },
+                    Instruction::Annotated {
+                        terms: let_terms,
+                        instruction: let_instruction,
+                    },
+                    Instruction::Annotated {
+                        terms: eval_terms, ..
+                    },
+                ],
+            ) => {
+                assert_eq!(name, "Nat");
+                assert_eq!(ty.to_string(), "forall a. (a -> a) -> a -> a");
+                assert_eq!(let_terms[0].0.to_string(), "Λa. λx: a. x");
+                // The instruction runs on the erased term
+                assert!(matches!(
+                    let_instruction.as_ref(),
+                    Instruction::Let { lambda_term, .. } if *lambda_term == parse_term("\\x. x")
+                ));
+                assert_eq!(eval_terms[0].0.to_string(), "(id [Nat -> forall b. b] 2)");
+            }
+            result => panic!(
+                "Expected an alias and two annotated instructions, got {:?}",
+                result
+            ),
+        }
+
+        // Terms without types are not type-checked in System F
+        assert!(matches!(
+            parse("let id = \\x. x").as_deref(),
+            Ok([Instruction::Let { .. }])
+        ));
+    }
+
+    #[test]
+    fn test_keyword_hints() {
+        let hint = |source: &str| {
+            let tokens: Vec<_> = lex(source)
+                .expect("valid tokens")
+                .into_iter()
+                .map(|token| token.token_type)
+                .collect();
+            let errors = parser()
+                .parse(tokens.as_slice())
+                .into_result()
+                .expect_err("invalid program");
+            keyword_hint(&errors[0])
+        };
+        for (source, keyword) in [
+            (r"let in = \x. x", "in"),
+            (r"eval \rec. rec", "rec"),
+            ("eval f step", "step"),
+            ("let f = type", "type"),
+            (r"let f = \x. type", "type"),
+            ("module use { }", "use"),
+            ("import Bool (show)", "show"),
+        ] {
+            assert_eq!(
+                hint(source),
+                Some(format!(
+                    "`{}` is a keyword, and cannot be used as a name",
+                    keyword
+                )),
+                "{}",
+                source
+            );
+        }
+
+        // Keywords found where no name is expected get no hint
+        assert_eq!(hint("let = x"), None);
+        assert_eq!(hint("eval (f"), None);
+    }
+
+    #[test]
+    fn test_error_spans() {
+        // The missing dot is reported on the closing parenthesis
+        let errors = parse("eval (f \\x y)").expect_err("missing dot");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, SimpleSpan::from(6..7));
+
+        // A name is not expected to be followed by a dot
+        let errors = parse("eval (f").expect_err("unclosed parenthesis");
+        assert!(!errors[0].1.contains("dot"), "{}", errors[0].1);
+        assert_eq!(
+            parse_term("Bool.not Bool.true"),
+            parse_term("(Bool.not Bool.true)")
+        );
+
+        // The unclosed parenthesis is reported at the end of the line
+        let errors = parse("eval f (g a\neval b").expect_err("unclosed parenthesis");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, SimpleSpan::from(5..6));
+
+        // A number literal too large to be built is reported on the literal
+        assert!(parse(&format!("eval {}", MAX_NUMERAL)).is_ok());
+        let errors = parse(&format!("eval f {}", MAX_NUMERAL + 1)).expect_err("large literal");
+        assert_eq!(
+            errors,
+            vec![(
+                SimpleSpan::from(2..3),
+                format!("number literals are at most {}", MAX_NUMERAL)
+            )]
+        );
+    }
 }
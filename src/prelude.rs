@@ -0,0 +1,71 @@
+//! The standard prelude, a lambca program embedded in the interpreter and
+//! loaded by `use prelude`.
+
+/// The name given to the prelude in ariadne reports.
+pub const NAME: &str = "<prelude>";
+
+/// The source of the prelude.
+pub const SOURCE: &str = include_str!("prelude.lambca");
+
+#[cfg(test)]
+mod tests {
+    use std::sync::RwLock;
+
+    use chumsky::Parser;
+
+    use super::*;
+    use crate::algo::{Backend, Instruction, Settings, Target};
+    use crate::environment::Environment;
+    use crate::lexer::lex;
+
+    /// Parse a source into instructions.
+    fn parse(source: &str) -> Vec<Instruction> {
+        let tokens: Vec<_> = lex(source)
+            .expect("valid tokens")
+            .into_iter()
+            .map(|token| token.token_type)
+            .collect();
+        crate::parser::parser()
+            .parse(tokens.as_slice())
+            .into_result()
+            .expect("valid program")
+            .into_iter()
+            .map(|(instruction, _)| instruction)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_prelude_definitions() {
+        let checks = "
+            assert_eq (if (xor true false) 1 2) 1
+            assert_eq (snd (pair 1 2)) 2
+            assert_eq (pred (exp 2 3)) 7
+            assert_eq (eq (sub 5 2) 3) true
+            assert_eq (leq 4 3) false
+            assert_eq (length (append (cons 1 nil) (cons 2 nil))) 2
+            assert_eq (foldr plus 0 (map succ (cons 1 (cons 2 nil)))) 5
+            assert_eq (head (tail (cons 1 (cons 2 nil)))) 2
+            assert_eq (isnil nil) true
+        ";
+        // Both evaluators reach the same normal forms
+        for backend in [Backend::Substitution, Backend::Lazy, Backend::Krivine] {
+            let save_lambda_term = RwLock::new(Environment::new());
+            let settings = Settings {
+                strategy: Default::default(),
+                max_steps: 10_000,
+                trace: false,
+                style: Default::default(),
+                view: Default::default(),
+                typed: false,
+                target: Target::Beta,
+                backend,
+            };
+            for instruction in parse(SOURCE).into_iter().chain(parse(checks)) {
+                instruction
+                    .compute(&save_lambda_term, &settings)
+                    .await
+                    .expect("the prelude should behave as documented");
+            }
+        }
+    }
+}
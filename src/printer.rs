@@ -0,0 +1,370 @@
+//! A pretty-printer for lambda terms, with a choice of output styles.
+
+use std::fmt::Display;
+use std::ops::Range;
+use std::str::FromStr;
+
+use logos::Logos;
+
+use crate::algo::{Direction, LambdaTerm};
+use crate::lexer::TokenType;
+
+/// A way of writing lambda terms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Style {
+    /// The textbook notation: applications are written by juxtaposition and
+    /// associate to the left, lambda bodies extend as far right as possible,
+    /// and parentheses only appear where they are needed, as in
+    /// `λf.λx.f (f x)`.
+    Compact,
+
+    /// The lambca input syntax, with every application between parentheses
+    /// and `\` for lambda, as in `\f.\x.(f (f x))`.
+    Lambca,
+
+    /// The lambca input syntax with `λ` for lambda, as in `λf.λx.(f (f x))`.
+    #[default]
+    Unicode,
+}
+
+impl Style {
+    /// All the styles, with the name used to select them.
+    pub const ALL: [(&str, Self); 3] = [
+        ("compact", Self::Compact),
+        ("lambca", Self::Lambca),
+        ("unicode", Self::Unicode),
+    ];
+
+    /// The symbol written before the parameter of a lambda abstraction.
+    const fn lambda(self) -> &'static str {
+        match self {
+            Self::Lambca => "\\",
+            Self::Compact | Self::Unicode => "λ",
+        }
+    }
+
+    /// Whether every application is written between parentheses.
+    const fn parenthesizes_applications(self) -> bool {
+        !matches!(self, Self::Compact)
+    }
+
+    /// Whether the term needs grouping parentheses at the given position.
+    const fn needs_parentheses(self, term: &LambdaTerm, position: Position) -> bool {
+        match (term, position) {
+            // The body of the lambda would take in what follows it
+            (LambdaTerm::LambdaAbstraction(..), Position::Function) => true,
+            (LambdaTerm::LambdaAbstraction(..), Position::Argument { last }) => !last,
+            (LambdaTerm::Application(..), Position::Argument { .. }) => {
+                !self.parenthesizes_applications()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Style {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, style)| *style)
+            .ok_or_else(|| {
+                let names = Self::ALL.map(|(name, _)| name).join(", ");
+                format!("unknown style '{}', expected one of: {}", s, names)
+            })
+    }
+}
+
+impl Display for Style {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Compact => write!(f, "compact"),
+            Self::Lambca => write!(f, "lambca"),
+            Self::Unicode => write!(f, "unicode"),
+        }
+    }
+}
+
+/// The place of a subterm in the term being written, deciding whether it
+/// needs grouping parentheses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Position {
+    /// Nothing follows the subterm: it is the whole term, the body of a
+    /// lambda abstraction or the inside of parentheses.
+    End,
+
+    /// The function of an application.
+    Function,
+
+    /// The argument of an application, the `last` one if nothing follows the
+    /// application.
+    Argument {
+        /// Whether nothing follows the argument.
+        last: bool,
+    },
+}
+
+/// A term being written, along with the range of characters taken by the
+/// marked subterm.
+struct Writer {
+    /// The style the term is written in.
+    style: Style,
+
+    /// The text written so far.
+    text: String,
+
+    /// The number of characters of the text written so far.
+    length: usize,
+
+    /// The range of characters taken by the marked subterm.
+    mark: Range<usize>,
+}
+
+impl Writer {
+    /// Append a piece of text.
+    fn push(&mut self, text: &str) {
+        self.text.push_str(text);
+        self.length += text.chars().count();
+    }
+
+    /// Write a term at the given position. The subterm at the end of `path`
+    /// is marked, `None` meaning that it is not inside this term.
+    fn write(&mut self, term: &LambdaTerm, position: Position, path: Option<&[Direction]>) {
+        let start = self.length;
+        let grouped = self.style.needs_parentheses(term, position);
+        let position = if grouped { Position::End } else { position };
+        if grouped {
+            self.push("(");
+        }
+
+        match term {
+            LambdaTerm::Variable(name) => self.push(name),
+            LambdaTerm::LambdaAbstraction(param, body) => {
+                // A parameter named after a keyword, as the one of the
+                // fixpoint combinator of `let rec`, is renamed so that the
+                // term can be parsed back
+                let renamed = (!is_identifier(param)).then(|| rename_parameter(param, body));
+                let (param, body) = match &renamed {
+                    Some((param, body)) => (param.as_str(), body),
+                    None => (param.as_str(), body.as_ref()),
+                };
+                self.push(self.style.lambda());
+                self.push(param);
+                self.push(".");
+                self.write(body, Position::End, follow(path, Direction::Body));
+            }
+            LambdaTerm::Application(func, arg) => {
+                let parenthesized = self.style.parenthesizes_applications();
+                if parenthesized {
+                    self.push("(");
+                }
+                self.write(func, Position::Function, follow(path, Direction::Function));
+                self.push(" ");
+                let last = parenthesized || position == Position::End;
+                self.write(
+                    arg,
+                    Position::Argument { last },
+                    follow(path, Direction::Argument),
+                );
+                if parenthesized {
+                    self.push(")");
+                }
+            }
+        }
+
+        if grouped {
+            self.push(")");
+        }
+        if path.is_some_and(<[Direction]>::is_empty) {
+            self.mark = start..self.length;
+        }
+    }
+}
+
+/// Whether the name is lexed as an identifier, and not as a keyword.
+fn is_identifier(name: &str) -> bool {
+    matches!(
+        TokenType::lexer(name).collect::<Vec<_>>().as_slice(),
+        [Ok(TokenType::Identifier(_))]
+    )
+}
+
+/// The parameter of `λparam.body` renamed to an identifier that is not free
+/// in the body, along with the body referring to it.
+fn rename_parameter(param: &str, body: &LambdaTerm) -> (String, LambdaTerm) {
+    let free = body.free_variables();
+    let fresh = (1..)
+        .map(|n| format!("{}{}", param, n))
+        .find(|name| !free.contains(name) && is_identifier(name))
+        .expect("there are infinitely many names");
+    let body = body.clone().rename_free_variable(param, &fresh);
+    (fresh, body)
+}
+
+/// The rest of the path once the given direction is taken, `None` if the path
+/// goes elsewhere.
+fn follow(path: Option<&[Direction]>, direction: Direction) -> Option<&[Direction]> {
+    path.and_then(<[Direction]>::split_first)
+        .filter(|(first, _)| **first == direction)
+        .map(|(_, rest)| rest)
+}
+
+/// Write the term in the given style.
+///
+/// The parameters named after a keyword are renamed, so that the text is
+/// parsed back to the same term, up to the renaming of bound variables, as
+/// long as its free variables are not keywords.
+pub fn print(term: &LambdaTerm, style: Style) -> String {
+    print_marked(term, style, &[]).0
+}
+
+/// Write the term in the given style, along with the range of characters
+/// taken by the subterm at the given path.
+pub fn print_marked(term: &LambdaTerm, style: Style, path: &[Direction]) -> (String, Range<usize>) {
+    let mut writer = Writer {
+        style,
+        text: String::new(),
+        length: 0,
+        mark: 0..0,
+    };
+    writer.write(term, Position::End, Some(path));
+    (writer.text, writer.mark)
+}
+
+/// A lambda term along with the style to display it in, as returned by
+/// `LambdaTerm::styled`.
+#[derive(Debug, Clone, Copy)]
+pub struct Styled<'a> {
+    /// The term to display.
+    term: &'a LambdaTerm,
+
+    /// The style to display the term in.
+    style: Style,
+}
+
+impl Display for Styled<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&print(self.term, self.style))
+    }
+}
+
+impl LambdaTerm {
+    /// Display the term in the given style.
+    pub const fn styled(&self, style: Style) -> Styled<'_> {
+        Styled { term: self, style }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::test_support::try_parse_term;
+
+    /// An arbitrary lambda term over a few names, so that variables are often
+    /// bound and shadowed, some of them being keywords.
+    fn lambda_term() -> impl Strategy<Value = LambdaTerm> {
+        let name = prop::sample::select(vec![
+            "x", "y", "f", "α", "x1", "in", "in1", "where", "rec", "type", "step", "show", "use",
+            "let", "eval", "forall",
+        ])
+        .prop_map(String::from);
+        let term =
+            name.clone()
+                .prop_map(LambdaTerm::Variable)
+                .prop_recursive(6, 48, 2, move |inner| {
+                    prop_oneof![
+                        (name.clone(), inner.clone()).prop_map(|(param, body)| {
+                            LambdaTerm::LambdaAbstraction(param, Box::new(body))
+                        }),
+                        (inner.clone(), inner).prop_map(|(func, arg)| {
+                            LambdaTerm::Application(Box::new(func), Box::new(arg))
+                        }),
+                    ]
+                });
+        // The free variables named after keywords are bound, as they cannot
+        // be written
+        term.prop_map(|term| {
+            let mut free: Vec<String> = term.free_variables().into_iter().collect();
+            free.sort();
+            free.into_iter()
+                .filter(|name| !is_identifier(name))
+                .fold(term, |term, name| {
+                    LambdaTerm::LambdaAbstraction(name, Box::new(term))
+                })
+        })
+    }
+
+    #[test]
+    fn test_styles() {
+        let term = try_parse_term("\\f.(\\x.f x) (λy.y) (f f \\z.z)").expect("valid term");
+        assert_eq!(
+            print(&term, Style::Compact),
+            "λf.(λx.f x) (λy.y) (f f λz.z)"
+        );
+        assert_eq!(
+            print(&term, Style::Lambca),
+            "\\f.(((\\x.(f x)) \\y.y) ((f f) \\z.z))"
+        );
+        assert_eq!(
+            print(&term, Style::Unicode),
+            "λf.(((λx.(f x)) λy.y) ((f f) λz.z))"
+        );
+    }
+
+    #[test]
+    fn test_print_marked() {
+        let term = try_parse_term("\\f.(\\x.f x) (λy.y) (f f \\z.z)").expect("valid term");
+        let path = [Direction::Body, Direction::Function];
+        assert_eq!(
+            print_marked(&term, Style::Compact, &path),
+            ("λf.(λx.f x) (λy.y) (f f λz.z)".to_string(), 3..18)
+        );
+        assert_eq!(
+            print_marked(&term, Style::Unicode, &path),
+            ("λf.(((λx.(f x)) λy.y) ((f f) λz.z))".to_string(), 4..21)
+        );
+    }
+
+    #[test]
+    fn test_keyword_parameters() {
+        let var = |name: &str| Box::new(LambdaTerm::Variable(name.to_string()));
+        // λx.in (in1 x), where `in` is bound by the fixpoint
+        let body = LambdaTerm::LambdaAbstraction(
+            "x".to_string(),
+            Box::new(LambdaTerm::Application(
+                var("in"),
+                Box::new(LambdaTerm::Application(var("in1"), var("x"))),
+            )),
+        );
+        assert_eq!(
+            print(&LambdaTerm::fixpoint("in", body), Style::Compact),
+            "(λrec1.(λx.rec1 (x x)) λx.rec1 (x x)) λin2.λx.in2 (in1 x)"
+        );
+    }
+
+    #[test]
+    fn test_style_from_str() {
+        assert_eq!("compact".parse(), Ok(Style::Compact));
+        assert!("fancy".parse::<Style>().is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_printed_terms_parse_back(term in lambda_term()) {
+            for (_, style) in Style::ALL {
+                let parsed = try_parse_term(&print(&term, style));
+                prop_assert!(
+                    parsed.as_ref().is_some_and(|parsed| parsed.alpha_eq(&term)),
+                    "{} in the {} style is parsed back as {:?}",
+                    term.styled(style),
+                    style,
+                    parsed
+                );
+            }
+        }
+    }
+}
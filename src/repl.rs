@@ -0,0 +1,263 @@
+//! The interactive session of the lambda calculus interpreter.
+
+use std::fs;
+use std::ops::ControlFlow;
+use std::process::exit;
+use std::sync::RwLock;
+
+use lambca::algo::Settings;
+use lambca::environment::Environment;
+use lambca::lexer::TokenType;
+use logos::Logos;
+use rustyline::DefaultEditor;
+use rustyline::error::ReadlineError;
+
+/// The name given to the source of the entries in ariadne reports.
+const SOURCE_NAME: &str = "<repl>";
+
+/// The help message listing the meta-commands.
+const HELP: &str = "\
+Enter instructions as in a lambca file, or one of these commands:
+  :env          list the bound names, with their types
+  :reset        remove every bound name
+  :load <file>  run a lambca file in the session
+  :help         print this message
+  :quit         leave the session";
+
+/// Run the read-eval-print loop until the user leaves it.
+/// The names bound by `let` instructions are kept from one entry to the next.
+pub async fn run(settings: &Settings) {
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("Could not start the interactive session: {}", e);
+            exit(1);
+        }
+    };
+
+    println!(
+        "lambca {}, type :help for the list of commands",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    let save_lambda_term = RwLock::new(Environment::new());
+
+    while let Some(entry) = read_entry(&mut editor) {
+        // A failure to save the history only loses the entry for the arrow keys.
+        let _ = editor.add_history_entry(entry.as_str());
+
+        if let Some(command) = entry.trim().strip_prefix(':') {
+            if command_run(command, &save_lambda_term, settings)
+                .await
+                .is_break()
+            {
+                break;
+            }
+        } else if let Some(program) = crate::parse_source(SOURCE_NAME, &entry) {
+            crate::run_program(
+                program,
+                SOURCE_NAME,
+                &entry,
+                &save_lambda_term,
+                settings,
+                &mut Vec::new(),
+            )
+            .await;
+        }
+    }
+}
+
+/// Read an entry from the user, going on over several lines while its
+/// parentheses or braces are unbalanced.
+/// Returns `None` if the user closes the input.
+fn read_entry(editor: &mut DefaultEditor) -> Option<String> {
+    let mut entry = String::new();
+    loop {
+        let prompt = if entry.is_empty() { "λ> " } else { ".. " };
+        match editor.readline(prompt) {
+            Ok(line) => {
+                push_line(&mut entry, &line);
+                if parentheses_depth(&entry) <= 0 && braces_depth(&entry) <= 0 {
+                    return Some(entry);
+                }
+            }
+            // Ctrl-C discards the entry being written.
+            Err(ReadlineError::Interrupted) => entry.clear(),
+            Err(_) => return None,
+        }
+    }
+}
+
+/// Add a line to the entry, without its comment, which would otherwise extend
+/// over the next lines. The lines are joined with a space, as a newline would
+/// end the instruction, unless they are instructions of a module block, which
+/// are separated by newlines.
+fn push_line(entry: &mut String, line: &str) {
+    let end = TokenType::lexer(line)
+        .spanned()
+        .last()
+        .map_or(0, |(_, span)| span.end);
+    if end == 0 {
+        return;
+    }
+    if !entry.is_empty() {
+        let in_block = braces_depth(entry) > 0 && parentheses_depth(entry) <= 0;
+        entry.push(if in_block { '\n' } else { ' ' });
+    }
+    entry.push_str(&line[..end]);
+}
+
+/// The number of parentheses opened and not closed in the text.
+fn parentheses_depth(text: &str) -> isize {
+    TokenType::lexer(text)
+        .filter_map(Result::ok)
+        .map(|token_type| match token_type {
+            TokenType::LParen => 1,
+            TokenType::RParen => -1,
+            _ => 0,
+        })
+        .sum()
+}
+
+/// The number of braces opened and not closed in the text.
+fn braces_depth(text: &str) -> isize {
+    TokenType::lexer(text)
+        .filter_map(Result::ok)
+        .map(|token_type| match token_type {
+            TokenType::LBrace => 1,
+            TokenType::RBrace => -1,
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Run a meta-command, given without its leading colon.
+/// Returns `ControlFlow::Break` if the session should end.
+async fn command_run(
+    command: &str,
+    save_lambda_term: &RwLock<Environment>,
+    settings: &Settings,
+) -> ControlFlow<()> {
+    let (name, argument) = command
+        .split_once(char::is_whitespace)
+        .map_or((command, ""), |(name, argument)| (name, argument.trim()));
+
+    match (name, argument) {
+        ("quit" | "q", "") => return ControlFlow::Break(()),
+        ("help" | "h", "") => println!("{}", HELP),
+        ("env", "") => {
+            let read = save_lambda_term
+                .read()
+                .expect("the saved lambda terms lock is poisoned");
+            let definitions = read.definitions();
+            for (name, ty) in read.types() {
+                let lambda_term = definitions[name].styled(settings.style);
+                match ty {
+                    Some(ty) => println!("{} : {} = {}", name, ty, lambda_term),
+                    None => println!("{} = {}", name, lambda_term),
+                }
+            }
+        }
+        ("reset", "") => save_lambda_term
+            .write()
+            .expect("the saved lambda terms lock is poisoned")
+            .clear(),
+        ("load", path) if !path.is_empty() => match fs::read_to_string(path) {
+            Ok(text) => {
+                if let Some(program) = crate::parse_source(path, &text) {
+                    crate::run_program(
+                        program,
+                        path,
+                        &text,
+                        save_lambda_term,
+                        settings,
+                        &mut Vec::new(),
+                    )
+                    .await;
+                }
+            }
+            Err(e) => eprintln!("Could not read file '{}': {}", path, e),
+        },
+        _ => eprintln!(
+            "Unknown command ':{}', type :help for the list of commands",
+            command
+        ),
+    }
+    ControlFlow::Continue(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chumsky::Parser;
+
+    use super::*;
+
+    /// The entry made of the lines, as read over several prompts.
+    fn entry(lines: &[&str]) -> String {
+        let mut entry = String::new();
+        for line in lines {
+            push_line(&mut entry, line);
+        }
+        entry
+    }
+
+    #[test]
+    fn test_parentheses_depth() {
+        assert_eq!(parentheses_depth("eval (\\x. x) (f (g a))"), 0);
+        assert_eq!(parentheses_depth("eval (f (g a)"), 1);
+        assert_eq!(parentheses_depth("eval (\\x. (x"), 2);
+        // An over-closed entry is complete, the parser reports the error
+        assert_eq!(parentheses_depth("eval f a)"), -1);
+        // Parentheses in comments do not count
+        assert_eq!(parentheses_depth("eval f // (a"), 0);
+        assert_eq!(parentheses_depth("eval (f // a)"), 1);
+    }
+
+    #[test]
+    fn test_entries_over_several_lines() {
+        let joined = entry(&["eval (f", "a)"]);
+        assert_eq!(joined, "eval (f a)");
+        assert_eq!(parentheses_depth(&joined), 0);
+
+        // A comment does not swallow the next lines
+        let joined = entry(&["eval (f // the function", "a) // the argument"]);
+        assert_eq!(joined, "eval (f a)");
+        assert_eq!(parentheses_depth(&joined), 0);
+
+        // A line holding only a comment adds nothing
+        assert_eq!(entry(&["eval (f", "// a comment", "a)"]), "eval (f a)");
+    }
+
+    #[test]
+    fn test_modules_over_several_lines() {
+        let lines = [
+            "module Bool {",
+            "let true = \\x y. x",
+            "let not = \\b. b (\\x y.",
+            "y) true",
+            "}",
+        ];
+        let joined = entry(&lines);
+        for (count, _) in lines.iter().enumerate().take(lines.len() - 1) {
+            assert!(braces_depth(&entry(&lines[..=count])) > 0);
+        }
+        assert_eq!(braces_depth(&joined), 0);
+
+        // The instructions of the block are kept on their own lines, and a
+        // term spread over several lines inside it is joined
+        assert_eq!(
+            joined,
+            "module Bool {\nlet true = \\x y. x\nlet not = \\b. b (\\x y. y) true\n}"
+        );
+        let tokens: Vec<_> = lambca::lexer::lex(&joined)
+            .expect("valid tokens")
+            .into_iter()
+            .map(|token| token.token_type)
+            .collect();
+        let instructions = lambca::parser::parser()
+            .parse(tokens.as_slice())
+            .into_result()
+            .expect("the module should parse");
+        assert_eq!(instructions.len(), 2);
+    }
+}
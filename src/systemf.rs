@@ -0,0 +1,917 @@
+//! System F, the polymorphic lambda calculus: explicitly typed terms, their
+//! type checking, and their erasure to untyped lambda terms.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+
+use chumsky::span::SimpleSpan;
+
+use crate::algo::LambdaTerm;
+use crate::encoding;
+use crate::environment::Environment;
+use crate::parser::Spanned;
+use crate::types::{self, Scheme};
+
+/// A System F type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    /// A type variable, or the name of a type alias before it is expanded.
+    Variable(String),
+
+    /// The type `A -> B` of the functions from `A` to `B`.
+    Arrow(Box<Self>, Box<Self>),
+
+    /// The polymorphic type `forall a. T`, of the terms that have the type
+    /// `T` for every type `a`.
+    Forall(String, Box<Self>),
+}
+
+impl Type {
+    /// The type variables of the type that are not bound by a `forall`.
+    fn free_variables(&self) -> HashSet<String> {
+        match self {
+            Self::Variable(name) => HashSet::from([name.clone()]),
+            Self::Arrow(from, to) => {
+                let mut free = from.free_variables();
+                free.extend(to.free_variables());
+                free
+            }
+            Self::Forall(var, body) => {
+                let mut free = body.free_variables();
+                free.remove(var);
+                free
+            }
+        }
+    }
+
+    /// Replace the free occurrences of a type variable by a type, renaming
+    /// the bound variables that would capture the free variables of the type.
+    fn substitute(&self, var: &str, replacement: &Self) -> Self {
+        match self {
+            Self::Variable(name) if name == var => replacement.clone(),
+            Self::Variable(_) => self.clone(),
+            Self::Arrow(from, to) => Self::Arrow(
+                Box::new(from.substitute(var, replacement)),
+                Box::new(to.substitute(var, replacement)),
+            ),
+            Self::Forall(bound, _) if bound == var => self.clone(),
+            Self::Forall(bound, body) => {
+                let mut avoided = replacement.free_variables();
+                avoided.extend(body.free_variables());
+                if replacement.free_variables().contains(bound) {
+                    let fresh = fresh_name(bound, &avoided);
+                    let body = body.substitute(bound, &Self::Variable(fresh.clone()));
+                    Self::Forall(fresh, Box::new(body.substitute(var, replacement)))
+                } else {
+                    Self::Forall(bound.clone(), Box::new(body.substitute(var, replacement)))
+                }
+            }
+        }
+    }
+
+    /// Whether the two types are equal up to the renaming of their bound
+    /// variables.
+    pub fn alpha_eq(&self, other: &Self) -> bool {
+        Self::alpha_eq_in(self, other, &mut Vec::new())
+    }
+
+    /// Whether the two types are alpha-equivalent, `bound` pairing the
+    /// variables bound around each of them, the innermost ones last.
+    fn alpha_eq_in<'a>(
+        left: &'a Self,
+        right: &'a Self,
+        bound: &mut Vec<(&'a str, &'a str)>,
+    ) -> bool {
+        match (left, right) {
+            (Self::Variable(l), Self::Variable(r)) => {
+                let l_position = bound.iter().rposition(|(bound_l, _)| bound_l == l);
+                let r_position = bound.iter().rposition(|(_, bound_r)| bound_r == r);
+                match (l_position, r_position) {
+                    (None, None) => l == r,
+                    _ => l_position == r_position,
+                }
+            }
+            (Self::Arrow(l_from, l_to), Self::Arrow(r_from, r_to)) => {
+                Self::alpha_eq_in(l_from, r_from, bound) && Self::alpha_eq_in(l_to, r_to, bound)
+            }
+            (Self::Forall(l_var, l_body), Self::Forall(r_var, r_body)) => {
+                bound.push((l_var, r_var));
+                let equal = Self::alpha_eq_in(l_body, r_body, bound);
+                bound.pop();
+                equal
+            }
+            _ => false,
+        }
+    }
+
+    /// The System F type of a type scheme: its type, under a `forall` for
+    /// each of its variables.
+    fn from_scheme(scheme: &Scheme) -> Self {
+        /// The System F type of a simple type.
+        fn convert(ty: &types::Type) -> Type {
+            match ty {
+                types::Type::Variable(_) => Type::Variable(ty.to_string()),
+                types::Type::Arrow(from, to) => {
+                    Type::Arrow(Box::new(convert(from)), Box::new(convert(to)))
+                }
+            }
+        }
+        let ty = scheme.ty();
+        ty.variables()
+            .into_iter()
+            .rev()
+            .fold(convert(ty), |body, index| {
+                Self::Forall(types::Type::Variable(index).to_string(), Box::new(body))
+            })
+    }
+
+    /// The same type, where the free names are replaced by the types of the
+    /// aliases they name, `bound` holding the type variables in scope.
+    /// Fails with the first name that is neither bound nor an alias.
+    fn expand(
+        &self,
+        aliases: &HashMap<String, Self>,
+        bound: &mut Vec<String>,
+    ) -> Result<Self, String> {
+        match self {
+            Self::Variable(name) if bound.contains(name) => Ok(self.clone()),
+            Self::Variable(name) => aliases.get(name).cloned().ok_or_else(|| name.clone()),
+            Self::Arrow(from, to) => Ok(Self::Arrow(
+                Box::new(from.expand(aliases, bound)?),
+                Box::new(to.expand(aliases, bound)?),
+            )),
+            Self::Forall(var, body) => {
+                bound.push(var.clone());
+                let body = body.expand(aliases, bound);
+                bound.pop();
+                Ok(Self::Forall(var.clone(), Box::new(body?)))
+            }
+        }
+    }
+
+    /// Expand the aliases of a type written in a program, where every type
+    /// variable must be bound.
+    /// Fails with the first name that is neither bound nor an alias.
+    pub fn expand_aliases(&self, aliases: &HashMap<String, Self>) -> Result<Self, String> {
+        self.expand(aliases, &mut Vec::new())
+    }
+
+    /// The same type, where the subterms equal to a type alias are written
+    /// with the name of the alias.
+    pub fn abbreviated(&self, aliases: &HashMap<String, Self>) -> Self {
+        let mut names: Vec<_> = aliases.iter().collect();
+        names.sort_by_key(|(name, _)| *name);
+        if let Some((name, _)) = names.iter().find(|(_, alias)| alias.alpha_eq(self)) {
+            return Self::Variable(name.to_string());
+        }
+        match self {
+            Self::Variable(_) => self.clone(),
+            Self::Arrow(from, to) => Self::Arrow(
+                Box::new(from.abbreviated(aliases)),
+                Box::new(to.abbreviated(aliases)),
+            ),
+            Self::Forall(var, body) => {
+                Self::Forall(var.clone(), Box::new(body.abbreviated(aliases)))
+            }
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Variable(name) => write!(f, "{}", name),
+            // The arrow associates to the right, and the body of a `forall`
+            // extends as far right as possible
+            Self::Arrow(from, to) => match from.as_ref() {
+                Self::Variable(_) => write!(f, "{} -> {}", from, to),
+                Self::Arrow(..) | Self::Forall(..) => write!(f, "({}) -> {}", from, to),
+            },
+            // `forall a. forall b. T` is written `forall a b. T`
+            Self::Forall(var, body) => {
+                let mut vars = vec![var.as_str()];
+                let mut body = body.as_ref();
+                while let Self::Forall(var, inner) = body {
+                    vars.push(var);
+                    body = inner;
+                }
+                write!(f, "forall {}. {}", vars.join(" "), body)
+            }
+        }
+    }
+}
+
+/// A name based on `base` that is not in `used`.
+fn fresh_name(base: &str, used: &HashSet<String>) -> String {
+    (1..)
+        .map(|counter| format!("{}{}", base, counter))
+        .find(|candidate| !used.contains(candidate))
+        .unwrap_or_else(|| base.to_string())
+}
+
+/// A lambda term that may be explicitly typed, each subterm having the span
+/// of the tokens it was parsed from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypedTerm {
+    /// A variable, represented by its name.
+    Variable(String),
+
+    /// A lambda abstraction `\x: A. M`, represented by its parameter, the
+    /// type of the parameter if it is annotated, and its body.
+    Abstraction(String, Option<Type>, Box<Spanned<Self>>),
+
+    /// An application, represented by its function and argument.
+    Application(Box<Spanned<Self>>, Box<Spanned<Self>>),
+
+    /// A type abstraction `/\a. M`, represented by its type variable and its
+    /// body.
+    TypeAbstraction(String, Box<Spanned<Self>>),
+
+    /// A type application `M [T]`, represented by its term and type.
+    TypeApplication(Box<Spanned<Self>>, Type),
+
+    /// A number literal, standing for its Church numeral.
+    Numeral(usize),
+}
+
+impl TypedTerm {
+    /// The typed term of an untyped lambda term, every subterm having the
+    /// given span, for the terms that are not written in the source, as the
+    /// fixpoint combinator of `let rec`.
+    pub fn from_untyped(term: &LambdaTerm, span: SimpleSpan) -> Spanned<Self> {
+        let typed = match term {
+            LambdaTerm::Variable(name) => Self::Variable(name.clone()),
+            LambdaTerm::LambdaAbstraction(param, body) => Self::Abstraction(
+                param.clone(),
+                None,
+                Box::new(Self::from_untyped(body, span)),
+            ),
+            LambdaTerm::Application(func, arg) => Self::Application(
+                Box::new(Self::from_untyped(func, span)),
+                Box::new(Self::from_untyped(arg, span)),
+            ),
+        };
+        (typed, span)
+    }
+
+    /// The fixpoint of the term `λname: A. value`, as built by `let rec`: the
+    /// term bound to the value with `name` standing for the term itself.
+    /// The annotations of the value are kept, and the type of the name, if it
+    /// is given, is the one of the recursive binding.
+    pub fn fixpoint(
+        name: String,
+        annotation: Option<Type>,
+        value: Spanned<Self>,
+        span: SimpleSpan,
+    ) -> Spanned<Self> {
+        let function = (Self::Abstraction(name, annotation, Box::new(value)), span);
+        let combinator = Self::from_untyped(&LambdaTerm::fixpoint_combinator(), span);
+        (
+            Self::Application(Box::new(combinator), Box::new(function)),
+            span,
+        )
+    }
+
+    /// The name, its type if it is given, and the value of the recursive
+    /// binding, if the term was built by [`Self::fixpoint`].
+    pub fn as_fixpoint(&self) -> Option<(&str, Option<&Type>, &Spanned<Self>)> {
+        let Self::Application(func, arg) = self else {
+            return None;
+        };
+        let Self::Abstraction(name, annotation, value) = &arg.0 else {
+            return None;
+        };
+        (!func.0.is_annotated() && func.0.erase() == LambdaTerm::fixpoint_combinator()).then_some((
+            name.as_str(),
+            annotation.as_ref(),
+            value.as_ref(),
+        ))
+    }
+
+    /// The untyped lambda term obtained by erasing the types: annotations,
+    /// type abstractions and type applications.
+    pub fn erase(&self) -> LambdaTerm {
+        match self {
+            Self::Variable(name) => LambdaTerm::Variable(name.clone()),
+            Self::Abstraction(param, _, body) => {
+                LambdaTerm::LambdaAbstraction(param.clone(), Box::new(body.0.erase()))
+            }
+            Self::Application(func, arg) => {
+                LambdaTerm::Application(Box::new(func.0.erase()), Box::new(arg.0.erase()))
+            }
+            Self::TypeAbstraction(_, body) | Self::TypeApplication(body, _) => body.0.erase(),
+            Self::Numeral(n) => encoding::numeral(*n),
+        }
+    }
+
+    /// Whether the term holds types: a type annotation, a type abstraction or
+    /// a type application.
+    pub fn is_annotated(&self) -> bool {
+        match self {
+            Self::Variable(_) | Self::Numeral(_) => false,
+            Self::Abstraction(_, annotation, body) => annotation.is_some() || body.0.is_annotated(),
+            Self::Application(func, arg) => func.0.is_annotated() || arg.0.is_annotated(),
+            Self::TypeAbstraction(..) | Self::TypeApplication(..) => true,
+        }
+    }
+
+    /// Get all free variables in this term.
+    pub fn free_variables(&self) -> HashSet<String> {
+        self.erase().free_variables()
+    }
+
+    /// Rename the free occurrences of a variable, the new name being one that
+    /// no lambda of the term binds, as a qualified name.
+    pub fn rename_free_variable(self, name: &str, new_name: &str) -> Self {
+        let rename = |term: Box<Spanned<Self>>| {
+            let (term, span) = *term;
+            Box::new((term.rename_free_variable(name, new_name), span))
+        };
+        match self {
+            Self::Variable(v) if v == name => Self::Variable(new_name.to_string()),
+            Self::Abstraction(param, annotation, body) if param != name => {
+                Self::Abstraction(param, annotation, rename(body))
+            }
+            Self::Application(func, arg) => Self::Application(rename(func), rename(arg)),
+            Self::TypeAbstraction(var, body) => Self::TypeAbstraction(var, rename(body)),
+            Self::TypeApplication(body, ty) => Self::TypeApplication(rename(body), ty),
+            term => term,
+        }
+    }
+
+    /// Replace the free occurrences of a type variable in the types of the
+    /// term by a type variable, renaming the type abstractions that would
+    /// capture it.
+    fn rename_type_variable(&self, var: &str, new_var: &str) -> Self {
+        let rename =
+            |term: &Spanned<Self>| Box::new((term.0.rename_type_variable(var, new_var), term.1));
+        let replacement = Type::Variable(new_var.to_string());
+        match self {
+            Self::Variable(_) | Self::Numeral(_) => self.clone(),
+            Self::Abstraction(param, annotation, body) => Self::Abstraction(
+                param.clone(),
+                annotation
+                    .as_ref()
+                    .map(|ty| ty.substitute(var, &replacement)),
+                rename(body),
+            ),
+            Self::Application(func, arg) => Self::Application(rename(func), rename(arg)),
+            Self::TypeAbstraction(bound, _) if bound == var => self.clone(),
+            Self::TypeAbstraction(bound, body) if bound == new_var => {
+                let fresh = fresh_name(
+                    bound,
+                    &HashSet::from([var.to_string(), new_var.to_string()]),
+                );
+                let renamed = body.0.rename_type_variable(bound, &fresh);
+                Self::TypeAbstraction(fresh, rename(&(renamed, body.1)))
+            }
+            Self::TypeAbstraction(bound, body) => {
+                Self::TypeAbstraction(bound.clone(), rename(body))
+            }
+            Self::TypeApplication(body, ty) => {
+                Self::TypeApplication(rename(body), ty.substitute(var, &replacement))
+            }
+        }
+    }
+}
+
+impl Display for TypedTerm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Variable(name) => write!(f, "{}", name),
+            Self::Abstraction(param, None, body) => write!(f, "λ{}.{}", param, body.0),
+            Self::Abstraction(param, Some(ty), body) => write!(f, "λ{}: {}. {}", param, ty, body.0),
+            Self::Application(func, arg) => match func.0 {
+                Self::Abstraction(..) | Self::TypeAbstraction(..) => {
+                    write!(f, "(({}) {})", func.0, arg.0)
+                }
+                _ => write!(f, "({} {})", func.0, arg.0),
+            },
+            Self::TypeAbstraction(var, body) => write!(f, "Λ{}. {}", var, body.0),
+            Self::TypeApplication(body, ty) => match body.0 {
+                Self::Abstraction(..) | Self::TypeAbstraction(..) => {
+                    write!(f, "({}) [{}]", body.0, ty)
+                }
+                _ => write!(f, "{} [{}]", body.0, ty),
+            },
+            Self::Numeral(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+/// An error raised while type checking a System F term, with the span of the
+/// subterm it is about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeError {
+    /// An argument does not have the type of the parameter of the function.
+    Mismatch {
+        /// The type of the parameter.
+        expected: Type,
+
+        /// The type of the argument.
+        found: Type,
+
+        /// The span of the argument.
+        span: SimpleSpan,
+    },
+
+    /// A term applied to an argument is not a function.
+    NotAFunction {
+        /// The type of the term.
+        found: Type,
+
+        /// The span of the term.
+        span: SimpleSpan,
+    },
+
+    /// A term applied to a type is not polymorphic.
+    NotPolymorphic {
+        /// The type of the term.
+        found: Type,
+
+        /// The span of the term.
+        span: SimpleSpan,
+    },
+
+    /// The parameter of a lambda abstraction has no type annotation.
+    MissingAnnotation {
+        /// The name of the parameter.
+        param: String,
+
+        /// The span of the lambda abstraction.
+        span: SimpleSpan,
+    },
+
+    /// A variable is neither bound nor defined.
+    UnboundVariable {
+        /// The name of the variable.
+        name: String,
+
+        /// The span of the variable.
+        span: SimpleSpan,
+    },
+
+    /// A name is defined by a term that has no type.
+    Untyped {
+        /// The name.
+        name: String,
+
+        /// The span of the occurrence of the name.
+        span: SimpleSpan,
+    },
+
+    /// A type refers to a type variable that is neither bound nor an alias.
+    UnboundTypeVariable {
+        /// The name of the type variable.
+        name: String,
+
+        /// The span of the subterm holding the type.
+        span: SimpleSpan,
+    },
+}
+
+impl TypeError {
+    /// The span of the subterm the error is about.
+    pub const fn span(&self) -> SimpleSpan {
+        match self {
+            Self::Mismatch { span, .. }
+            | Self::NotAFunction { span, .. }
+            | Self::NotPolymorphic { span, .. }
+            | Self::MissingAnnotation { span, .. }
+            | Self::UnboundVariable { span, .. }
+            | Self::Untyped { span, .. }
+            | Self::UnboundTypeVariable { span, .. } => *span,
+        }
+    }
+
+    /// A hint on how to get rid of the error.
+    pub const fn help(&self) -> &'static str {
+        match self {
+            Self::Mismatch { .. } => {
+                "the argument must have the type of the parameter, up to the renaming of bound type variables"
+            }
+            Self::NotAFunction { .. } => {
+                "only terms of type `A -> B` can be applied to an argument"
+            }
+            Self::NotPolymorphic { .. } => {
+                "only terms of type `forall a. T` can be applied to a type, as in `id [A]`"
+            }
+            Self::MissingAnnotation { .. } => {
+                "in a typed term, parameters are annotated, as in `\\x: A. M`, unless the lambda is applied to an argument, and so are recursive bindings, as in `let rec f: A = M in N`"
+            }
+            Self::UnboundVariable { .. } => {
+                "a typed term cannot have free variables, and recursive definitions cannot be typed"
+            }
+            Self::Untyped { .. } => "annotate the definition of the name so that it has a type",
+            Self::UnboundTypeVariable { .. } => {
+                "type variables are bound by `forall a.` or `/\\a.`, and aliases are defined with `type Name = T`"
+            }
+        }
+    }
+
+    /// Additional details about the error.
+    pub fn note(&self) -> Option<String> {
+        match self {
+            Self::Mismatch {
+                expected, found, ..
+            } => Some(format!("expected: {}\n   found: {}", expected, found)),
+            _ => None,
+        }
+    }
+}
+
+impl Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mismatch {
+                expected, found, ..
+            } => write!(
+                f,
+                "this argument has type `{}`, but `{}` is expected",
+                found, expected
+            ),
+            Self::NotAFunction { found, .. } => {
+                write!(f, "this term of type `{}` is applied to an argument", found)
+            }
+            Self::NotPolymorphic { found, .. } => {
+                write!(f, "this term of type `{}` is applied to a type", found)
+            }
+            Self::MissingAnnotation { param, .. } => {
+                write!(f, "the parameter `{}` has no type annotation", param)
+            }
+            Self::UnboundVariable { name, .. } => write!(f, "`{}` is not bound", name),
+            Self::Untyped { name, .. } => write!(f, "the definition of `{}` has no type", name),
+            Self::UnboundTypeVariable { name, .. } => {
+                write!(f, "the type variable `{}` is not bound", name)
+            }
+        }
+    }
+}
+
+/// The state of the type checking of a term.
+struct Checker<'a> {
+    /// The definitions, with the types of the annotated ones and the type
+    /// aliases.
+    environment: &'a Environment,
+
+    /// The type variables bound by the enclosing type abstractions.
+    type_scope: Vec<String>,
+
+    /// The types of the parameters of the enclosing lambdas, the innermost
+    /// one last.
+    context: Vec<(String, Type)>,
+}
+
+impl Checker<'_> {
+    /// The type with its aliases expanded, every type variable of it being
+    /// in scope.
+    fn expand(&self, ty: &Type, span: SimpleSpan) -> Result<Type, TypeError> {
+        ty.expand(self.environment.aliases(), &mut self.type_scope.clone())
+            .map_err(|name| TypeError::UnboundTypeVariable { name, span })
+    }
+
+    /// The type written with the type aliases, for error messages.
+    fn abbreviated(&self, ty: &Type) -> Type {
+        ty.abbreviated(self.environment.aliases())
+    }
+
+    /// The type of a name that is not bound by an enclosing lambda: the type
+    /// of its annotated definition, or the type scheme of its definition.
+    fn global_type(&self, name: &str, span: SimpleSpan) -> Result<Type, TypeError> {
+        if let Some(ty) = self.environment.signature(name) {
+            return Ok(ty.clone());
+        }
+        let definitions = self.environment.definitions();
+        let Some(definition) = definitions.get(name) else {
+            return Err(TypeError::UnboundVariable {
+                name: name.to_string(),
+                span,
+            });
+        };
+        types::scheme(definition, definitions)
+            .map(|scheme| Type::from_scheme(&scheme))
+            .map_err(|_| TypeError::Untyped {
+                name: name.to_string(),
+                span,
+            })
+    }
+
+    /// The type of the term.
+    fn synthesize(&mut self, (term, span): &Spanned<TypedTerm>) -> Result<Type, TypeError> {
+        let span = *span;
+        // A recursive binding has the type given to its name, which is the
+        // type its value must have
+        if let Some((name, annotation, value)) = term.as_fixpoint() {
+            let Some(annotation) = annotation else {
+                return Err(TypeError::MissingAnnotation {
+                    param: name.to_string(),
+                    span,
+                });
+            };
+            let ty = self.expand(annotation, span)?;
+            let value_type = self.with_parameter(name, ty.clone(), value)?;
+            if !value_type.alpha_eq(&ty) {
+                return Err(TypeError::Mismatch {
+                    expected: self.abbreviated(&ty),
+                    found: self.abbreviated(&value_type),
+                    span: value.1,
+                });
+            }
+            return Ok(ty);
+        }
+        match term {
+            TypedTerm::Variable(name) => {
+                match self.context.iter().rev().find(|(param, _)| param == name) {
+                    Some((_, ty)) => Ok(ty.clone()),
+                    None => self.global_type(name, span),
+                }
+            }
+            TypedTerm::Abstraction(param, annotation, body) => {
+                let Some(annotation) = annotation else {
+                    return Err(TypeError::MissingAnnotation {
+                        param: param.clone(),
+                        span,
+                    });
+                };
+                let param_type = self.expand(annotation, span)?;
+                let body_type = self.with_parameter(param, param_type.clone(), body)?;
+                Ok(Type::Arrow(Box::new(param_type), Box::new(body_type)))
+            }
+            // A lambda applied to an argument, as in a local binding, takes
+            // the type of the argument for its parameter
+            TypedTerm::Application(func, arg)
+                if matches!(&func.0, TypedTerm::Abstraction(_, None, _)) =>
+            {
+                let TypedTerm::Abstraction(param, _, body) = &func.0 else {
+                    unreachable!("the function is an unannotated lambda")
+                };
+                let arg_type = self.synthesize(arg)?;
+                self.with_parameter(param, arg_type, body)
+            }
+            TypedTerm::Application(func, arg) => {
+                let func_type = self.synthesize(func)?;
+                let Type::Arrow(param_type, result_type) = func_type else {
+                    return Err(TypeError::NotAFunction {
+                        found: self.abbreviated(&func_type),
+                        span: func.1,
+                    });
+                };
+                let arg_type = self.synthesize(arg)?;
+                if !arg_type.alpha_eq(&param_type) {
+                    return Err(TypeError::Mismatch {
+                        expected: self.abbreviated(&param_type),
+                        found: self.abbreviated(&arg_type),
+                        span: arg.1,
+                    });
+                }
+                Ok(*result_type)
+            }
+            TypedTerm::TypeAbstraction(var, body) => {
+                // The type variable is renamed if it would capture the type
+                // variables in scope
+                let mut used: HashSet<String> = self.type_scope.iter().cloned().collect();
+                used.extend(self.context.iter().flat_map(|(_, ty)| ty.free_variables()));
+                let (var, renamed) = if used.contains(var) {
+                    let fresh = fresh_name(var, &used);
+                    let renamed = body.0.rename_type_variable(var, &fresh);
+                    (fresh, Some((renamed, body.1)))
+                } else {
+                    (var.clone(), None)
+                };
+                self.type_scope.push(var.clone());
+                let body_type = self.synthesize(renamed.as_ref().unwrap_or(body));
+                self.type_scope.pop();
+                Ok(Type::Forall(var, Box::new(body_type?)))
+            }
+            TypedTerm::TypeApplication(body, ty) => {
+                let body_type = self.synthesize(body)?;
+                let Type::Forall(var, inner) = body_type else {
+                    return Err(TypeError::NotPolymorphic {
+                        found: self.abbreviated(&body_type),
+                        span: body.1,
+                    });
+                };
+                Ok(inner.substitute(&var, &self.expand(ty, span)?))
+            }
+            TypedTerm::Numeral(_) => Ok(church_numeral_type()),
+        }
+    }
+
+    /// The type of the body of a lambda, its parameter having the given type.
+    fn with_parameter(
+        &mut self,
+        param: &str,
+        param_type: Type,
+        body: &Spanned<TypedTerm>,
+    ) -> Result<Type, TypeError> {
+        self.context.push((param.to_string(), param_type));
+        let body_type = self.synthesize(body);
+        self.context.pop();
+        body_type
+    }
+}
+
+/// The type `forall a. (a -> a) -> a -> a` of the Church numerals.
+fn church_numeral_type() -> Type {
+    let a = || Box::new(Type::Variable("a".to_string()));
+    let function = Type::Arrow(a(), a());
+    Type::Forall(
+        "a".to_string(),
+        Box::new(Type::Arrow(
+            Box::new(function),
+            Box::new(Type::Arrow(a(), a())),
+        )),
+    )
+}
+
+/// Type check a System F term, returning its type.
+///
+/// The parameters of the lambdas must be annotated, except for the lambdas
+/// applied to an argument. A free name has the type of its definition: the
+/// type of its annotated definition, or the type scheme of an untyped
+/// definition, quantified with a `forall` for each of its type variables.
+/// Fails with the subterm that is not well typed.
+pub fn check(term: &Spanned<TypedTerm>, environment: &Environment) -> Result<Type, TypeError> {
+    Checker {
+        environment,
+        type_scope: Vec::new(),
+        context: Vec::new(),
+    }
+    .synthesize(term)
+}
+
+#[cfg(test)]
+mod tests {
+    use chumsky::Parser;
+
+    use super::*;
+    use crate::algo::Instruction;
+    use crate::lexer::lex;
+    use crate::parser::parser;
+
+    /// Parse the term of a single `eval` instruction, with its spans.
+    fn parse(source: &str) -> Spanned<TypedTerm> {
+        let tokens: Vec<_> = lex(&format!("eval {}", source))
+            .expect("valid tokens")
+            .into_iter()
+            .map(|token| token.token_type)
+            .collect();
+        let instructions = parser()
+            .parse(tokens.as_slice())
+            .into_result()
+            .expect("valid source");
+        match instructions.into_iter().next() {
+            Some((Instruction::Annotated { mut terms, .. }, _)) => terms.remove(0),
+            Some((Instruction::Eval { lambda_term, .. }, span)) => {
+                TypedTerm::from_untyped(&lambda_term, span)
+            }
+            result => panic!("Expected an eval instruction, got {:?}", result),
+        }
+    }
+
+    /// The type of a term in an environment without definitions, as written.
+    fn type_of(source: &str) -> Result<String, TypeError> {
+        check(&parse(source), &Environment::new()).map(|ty| ty.to_string())
+    }
+
+    #[test]
+    fn test_polymorphic_terms() {
+        assert_eq!(
+            type_of("/\\a. \\x: a. x"),
+            Ok("forall a. a -> a".to_string())
+        );
+        assert_eq!(
+            type_of("/\\a b. \\x: a. \\y: b. x"),
+            Ok("forall a b. a -> b -> a".to_string())
+        );
+        // The polymorphic identity can be applied to itself
+        assert_eq!(
+            type_of("(/\\a. \\x: a. x) [forall a. a -> a] (/\\a. \\x: a. x)"),
+            Ok("forall a. a -> a".to_string())
+        );
+        // Numerals are Church numerals, and local bindings take the type of
+        // their value
+        assert_eq!(
+            type_of("let n = 2 in n [a -> a]"),
+            Err(TypeError::UnboundTypeVariable {
+                name: "a".to_string(),
+                span: SimpleSpan::from(6..12),
+            })
+        );
+        assert_eq!(
+            type_of("/\\a. let n = 2 in n [a]"),
+            Ok("forall a. (a -> a) -> a -> a".to_string())
+        );
+    }
+
+    #[test]
+    fn test_recursive_bindings() {
+        // The annotations of the value of a recursive binding are kept, the
+        // name having the type it is given
+        let term = parse("/\\a. let rec f: a -> a = \\x: a. f x in f");
+        assert_eq!(
+            check(&term, &Environment::new()).map(|ty| ty.to_string()),
+            Ok("forall a. a -> a".to_string())
+        );
+        assert_eq!(term.0.erase(), parse("let rec f = \\x. f x in f").0.erase());
+
+        // The value must have the type given to the name
+        assert!(matches!(
+            type_of("/\\a b. let rec f: a -> b = \\x: a. x in f"),
+            Err(TypeError::Mismatch { .. })
+        ));
+        assert!(matches!(
+            type_of("/\\a. let rec f = \\x: a. f x in f"),
+            Err(TypeError::MissingAnnotation { param, .. }) if param == "f"
+        ));
+    }
+
+    #[test]
+    fn test_type_abstractions_avoid_capture() {
+        // Instantiating `b` with `a` must not capture it in `forall a.`
+        let ty = Type::Forall(
+            "a".to_string(),
+            Box::new(Type::Arrow(
+                Box::new(Type::Variable("a".to_string())),
+                Box::new(Type::Variable("b".to_string())),
+            )),
+        );
+        let substituted = ty.substitute("b", &Type::Variable("a".to_string()));
+        assert_eq!(substituted.to_string(), "forall a1. a1 -> a");
+        assert!(!substituted.alpha_eq(&ty));
+        assert!(
+            Type::Forall("c".to_string(), Box::new(Type::Variable("c".to_string()))).alpha_eq(
+                &Type::Forall("d".to_string(), Box::new(Type::Variable("d".to_string())))
+            )
+        );
+
+        assert_eq!(
+            type_of("/\\a. (/\\b. /\\a. \\x: b. \\y: a. x) [a]"),
+            Ok("forall a a1. a -> a1 -> a".to_string())
+        );
+    }
+
+    #[test]
+    fn test_errors_point_at_the_subterm() {
+        // The argument `x` has the type `a` where `a -> a` is expected
+        assert_eq!(
+            type_of("/\\a. \\f: (a -> a) -> a. \\x: a. f x"),
+            Err(TypeError::Mismatch {
+                expected: Type::Arrow(
+                    Box::new(Type::Variable("a".to_string())),
+                    Box::new(Type::Variable("a".to_string()))
+                ),
+                found: Type::Variable("a".to_string()),
+                span: SimpleSpan::from(21..22),
+            })
+        );
+        assert!(matches!(
+            type_of("/\\a. \\x. x"),
+            Err(TypeError::MissingAnnotation { span, .. }) if span == SimpleSpan::from(4..8)
+        ));
+        // The inner abstractions of `/\a b. M` span from their parameter
+        let (term, span) = parse("/\\a b. \\x: a. x");
+        assert_eq!(span, SimpleSpan::from(1..11));
+        match term {
+            TypedTerm::TypeAbstraction(_, inner) => assert_eq!(inner.1, SimpleSpan::from(3..11)),
+            term => panic!("Expected a type abstraction, got {}", term),
+        }
+        assert!(matches!(
+            type_of("/\\a. \\x: a. x [a]"),
+            Err(TypeError::NotPolymorphic { span, .. }) if span == SimpleSpan::from(9..10)
+        ));
+        assert!(matches!(
+            type_of("/\\a. \\x: a. y"),
+            Err(TypeError::UnboundVariable { name, .. }) if name == "y"
+        ));
+    }
+
+    #[test]
+    fn test_definitions_and_aliases() {
+        let nat = parse("2").0;
+        let mut env = Environment::new();
+        env.define_alias("Nat".to_string(), church_numeral_type());
+        env.define("two".to_string(), nat.erase());
+
+        // An unannotated definition has the type of its type scheme
+        let ty = check(&parse("two"), &env).expect("typable definition");
+        assert!(ty.alpha_eq(&church_numeral_type()));
+        assert_eq!(ty.abbreviated(env.aliases()).to_string(), "Nat");
+
+        env.define("loop".to_string(), parse("(\\x. x x) (\\x. x x)").0.erase());
+        assert!(matches!(
+            check(&parse("\\n: Nat. loop"), &env),
+            Err(TypeError::Untyped { name, .. }) if name == "loop"
+        ));
+    }
+
+    #[test]
+    fn test_erasure() {
+        let erased = parse("(/\\a. \\x: a -> a. \\y: a. x y) [forall b. b] 2")
+            .0
+            .erase();
+        assert_eq!(erased.to_string(), "((λx.λy.(x y)) λf.λx.(f (f x)))");
+        assert!(!parse("(\\x. x) 2").0.is_annotated());
+        assert!(parse("f [a]").0.is_annotated());
+    }
+}
@@ -0,0 +1,39 @@
+//! Helpers shared by the tests of the modules.
+
+use std::collections::HashMap;
+
+use chumsky::Parser;
+
+use crate::algo::{Instruction, LambdaTerm};
+use crate::lexer::lex;
+
+/// Parse a single lambda term written in the lambca syntax, or `None` if the
+/// source is not a valid lambda term.
+pub fn try_parse_term(source: &str) -> Option<LambdaTerm> {
+    let tokens: Vec<_> = lex(&format!("eval {}", source))
+        .ok()?
+        .into_iter()
+        .map(|token| token.token_type)
+        .collect();
+    let instructions = crate::parser::parser()
+        .parse(tokens.as_slice())
+        .into_result()
+        .ok()?;
+    match instructions.into_iter().next() {
+        Some((Instruction::Eval { lambda_term, .. }, _)) => Some(lambda_term),
+        _ => None,
+    }
+}
+
+/// Parse a single lambda term written in the lambca syntax.
+pub fn parse_term(source: &str) -> LambdaTerm {
+    try_parse_term(source).unwrap_or_else(|| panic!("invalid lambda term: {}", source))
+}
+
+/// Build the saved lambda terms from `(name, source)` pairs.
+pub fn environment(definitions: &[(&str, &str)]) -> HashMap<String, LambdaTerm> {
+    definitions
+        .iter()
+        .map(|(name, source)| (name.to_string(), parse_term(source)))
+        .collect()
+}
@@ -0,0 +1,498 @@
+//! Simple types, and the inference of the principal simple type of a lambda
+//! term by unification.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+
+use crate::algo::LambdaTerm;
+use crate::printer::Style;
+
+/// A simple type, built from type variables and function types.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Type {
+    /// A type variable, written `a`, `b`, … `z`, `a1`, … by increasing index.
+    Variable(usize),
+
+    /// The type `A -> B` of the functions from `A` to `B`.
+    Arrow(Box<Self>, Box<Self>),
+}
+
+impl Type {
+    /// Whether the type variable of the given index appears in the type.
+    fn contains(&self, index: usize) -> bool {
+        match self {
+            Self::Variable(i) => *i == index,
+            Self::Arrow(from, to) => from.contains(index) || to.contains(index),
+        }
+    }
+
+    /// The indices of the type variables of the type, in the order they first
+    /// appear.
+    pub fn variables(&self) -> Vec<usize> {
+        let mut variables = Vec::new();
+        self.collect_variables(&mut variables);
+        variables
+    }
+
+    /// Add the indices of the type variables of the type to `variables`,
+    /// the ones that are not there yet.
+    fn collect_variables(&self, variables: &mut Vec<usize>) {
+        match self {
+            Self::Variable(index) => {
+                if !variables.contains(index) {
+                    variables.push(*index);
+                }
+            }
+            Self::Arrow(from, to) => {
+                from.collect_variables(variables);
+                to.collect_variables(variables);
+            }
+        }
+    }
+
+    /// The same type, with its variables renumbered from 0 in the order they
+    /// appear, so that equal types up to renaming are written the same way.
+    pub fn normalized(&self) -> Self {
+        self.renumbered(&mut HashMap::new())
+    }
+
+    /// The same type, with its variables renumbered by `numbers`, new ones
+    /// getting the next numbers.
+    fn renumbered(&self, numbers: &mut HashMap<usize, usize>) -> Self {
+        match self {
+            Self::Variable(index) => {
+                let next = numbers.len();
+                Self::Variable(*numbers.entry(*index).or_insert(next))
+            }
+            Self::Arrow(from, to) => {
+                let from = from.renumbered(numbers);
+                Self::Arrow(Box::new(from), Box::new(to.renumbered(numbers)))
+            }
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Variable(index) => {
+                let letter = char::from(b'a' + (index % 26) as u8);
+                match index / 26 {
+                    0 => write!(f, "{}", letter),
+                    round => write!(f, "{}{}", letter, round),
+                }
+            }
+            // The arrow associates to the right
+            Self::Arrow(from, to) => match from.as_ref() {
+                Self::Arrow(..) => write!(f, "({}) -> {}", from, to),
+                Self::Variable(_) => write!(f, "{} -> {}", from, to),
+            },
+        }
+    }
+}
+
+/// A type scheme `forall a b. T`, the type of a definition that can be used at
+/// every type obtained by replacing its variables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scheme {
+    /// The type, all the variables of which are quantified.
+    ty: Type,
+}
+
+impl Scheme {
+    /// The type, all the variables of which are quantified.
+    pub const fn ty(&self) -> &Type {
+        &self.ty
+    }
+}
+
+impl Display for Scheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let variables = self.ty.variables();
+        if variables.is_empty() {
+            return write!(f, "{}", self.ty);
+        }
+        let names: Vec<_> = variables
+            .into_iter()
+            .map(|index| Type::Variable(index).to_string())
+            .collect();
+        write!(f, "forall {}. {}", names.join(" "), self.ty)
+    }
+}
+
+/// An error raised while inferring the type of a term.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeError {
+    /// The occurs check failed: typing a subterm needs a type variable
+    /// to be equal to a type containing it, which would be infinite.
+    InfiniteType {
+        /// The type variable.
+        variable: Type,
+
+        /// The type containing the variable.
+        containing: Type,
+
+        /// The subterm that cannot be typed.
+        term: LambdaTerm,
+    },
+}
+
+impl TypeError {
+    /// A hint on how to get rid of the error.
+    pub const fn help(&self) -> &'static str {
+        match self {
+            Self::InfiniteType { .. } => {
+                "a term applied to itself, as in `x x`, has no simple type, write recursive \
+                 definitions with `let rec` instead"
+            }
+        }
+    }
+
+    /// Additional details about the error, with the terms printed in the
+    /// given style.
+    pub fn note(&self, style: Style) -> String {
+        match self {
+            Self::InfiniteType { term, .. } => format!(
+                "the subterm that cannot be typed is:\n  {}",
+                term.styled(style)
+            ),
+        }
+    }
+}
+
+impl Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InfiniteType {
+                variable,
+                containing,
+                ..
+            } => write!(
+                f,
+                "occurs check failed: `{}` would have to be `{}`, an infinite type",
+                variable, containing
+            ),
+        }
+    }
+}
+
+/// The state of the inference of the type of a term.
+struct Inference<'a> {
+    /// The lambda terms bound by `let` instructions.
+    definitions: &'a HashMap<String, LambdaTerm>,
+
+    /// The types of the free names of the term that are not bound by `let`,
+    /// which are the same at each of their occurrences, and of the names
+    /// whose definition is being typed.
+    globals: HashMap<String, Type>,
+
+    /// The types of the names bound by `let` whose definition is typed, with
+    /// the indices of their quantified variables.
+    schemes: HashMap<String, (Vec<usize>, Type)>,
+
+    /// The types the type variables are found to be equal to.
+    substitution: HashMap<usize, Type>,
+
+    /// The index of the next fresh type variable.
+    next_variable: usize,
+}
+
+impl<'a> Inference<'a> {
+    /// A type variable that appears nowhere yet.
+    const fn fresh(&mut self) -> Type {
+        self.next_variable += 1;
+        Type::Variable(self.next_variable - 1)
+    }
+
+    /// A copy of the type, where the quantified variables are replaced by
+    /// fresh ones.
+    fn instantiate(&mut self, quantified: &[usize], ty: &Type) -> Type {
+        let numbers: HashMap<usize, usize> = quantified
+            .iter()
+            .map(|index| (*index, self.next_variable + index))
+            .collect();
+        self.next_variable += quantified.iter().max().map_or(0, |max| max + 1);
+        Self::replace_variables(ty, &numbers)
+    }
+
+    /// The type with the variables in `numbers` replaced by the ones they are
+    /// mapped to.
+    fn replace_variables(ty: &Type, numbers: &HashMap<usize, usize>) -> Type {
+        match ty {
+            Type::Variable(index) => Type::Variable(*numbers.get(index).unwrap_or(index)),
+            Type::Arrow(from, to) => Type::Arrow(
+                Box::new(Self::replace_variables(from, numbers)),
+                Box::new(Self::replace_variables(to, numbers)),
+            ),
+        }
+    }
+
+    /// The type of the definition of a name bound by `let`, generalised over
+    /// the type variables that do not appear in the types of `globals`.
+    /// The name has a single type in its own definition.
+    fn generalize(
+        &mut self,
+        name: &str,
+        definition: &LambdaTerm,
+    ) -> Result<(Vec<usize>, Type), TypeError> {
+        let ty = self.fresh();
+        self.globals.insert(name.to_string(), ty.clone());
+        let definition_type = self.infer(definition, &mut Vec::new())?;
+        self.unify(&ty, &definition_type)
+            .map_err(|(index, containing)| self.infinite_type(index, &containing, definition))?;
+        self.globals.remove(name);
+
+        let fixed: HashSet<usize> = self
+            .globals
+            .values()
+            .flat_map(|global| self.resolve(global).variables())
+            .collect();
+        let ty = self.resolve(&ty);
+        let quantified = ty
+            .variables()
+            .into_iter()
+            .filter(|index| !fixed.contains(index))
+            .collect();
+        Ok((quantified, ty))
+    }
+
+    /// The type with the substitution applied to all its variables.
+    fn resolve(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Variable(index) => self
+                .substitution
+                .get(index)
+                .map_or_else(|| ty.clone(), |bound| self.resolve(bound)),
+            Type::Arrow(from, to) => {
+                Type::Arrow(Box::new(self.resolve(from)), Box::new(self.resolve(to)))
+            }
+        }
+    }
+
+    /// Make both types equal by extending the substitution.
+    /// Fails with the variable and the type containing it if the occurs
+    /// check fails.
+    fn unify(&mut self, left: &Type, right: &Type) -> Result<(), (usize, Type)> {
+        match (self.resolve(left), self.resolve(right)) {
+            (Type::Variable(i), Type::Variable(j)) if i == j => Ok(()),
+            (Type::Variable(index), ty) | (ty, Type::Variable(index)) => {
+                if ty.contains(index) {
+                    return Err((index, ty));
+                }
+                self.substitution.insert(index, ty);
+                Ok(())
+            }
+            (Type::Arrow(left_from, left_to), Type::Arrow(right_from, right_to)) => {
+                self.unify(&left_from, &right_from)?;
+                self.unify(&left_to, &right_to)
+            }
+        }
+    }
+
+    /// The type of the term, `context` holding the types of the parameters of
+    /// the enclosing lambdas, the innermost one last.
+    fn infer<'t>(
+        &mut self,
+        term: &'t LambdaTerm,
+        context: &mut Vec<(&'t str, Type)>,
+    ) -> Result<Type, TypeError> {
+        // A recursive definition has the type of its body, where its name is
+        // bound to that same type
+        if let Some((name, body)) = term.as_fixpoint() {
+            let ty = self.fresh();
+            context.push((name, ty.clone()));
+            let body_type = self.infer(body, context);
+            context.pop();
+            self.unify(&ty, &body_type?)
+                .map_err(|(index, containing)| self.infinite_type(index, &containing, term))?;
+            return Ok(ty);
+        }
+
+        match term {
+            LambdaTerm::Variable(name) => {
+                if let Some((_, ty)) = context.iter().rev().find(|(param, _)| param == name) {
+                    return Ok(ty.clone());
+                }
+                if let Some(ty) = self.globals.get(name) {
+                    return Ok(ty.clone());
+                }
+                let definitions = self.definitions;
+                let Some(definition) = definitions.get(name) else {
+                    // Any other free name has an unknown type
+                    let ty = self.fresh();
+                    self.globals.insert(name.clone(), ty.clone());
+                    return Ok(ty);
+                };
+                // A name bound by `let` can be used at a different instance of
+                // the type of its definition at each occurrence
+                if !self.schemes.contains_key(name) {
+                    let scheme = self.generalize(name, definition)?;
+                    self.schemes.insert(name.clone(), scheme);
+                }
+                let (quantified, ty) = self.schemes[name].clone();
+                Ok(self.instantiate(&quantified, &ty))
+            }
+            LambdaTerm::LambdaAbstraction(param, body) => {
+                let param_type = self.fresh();
+                context.push((param, param_type.clone()));
+                let body_type = self.infer(body, context);
+                context.pop();
+                Ok(Type::Arrow(Box::new(param_type), Box::new(body_type?)))
+            }
+            LambdaTerm::Application(func, arg) => {
+                let func_type = self.infer(func, context)?;
+                let arg_type = self.infer(arg, context)?;
+                let result_type = self.fresh();
+                let expected = Type::Arrow(Box::new(arg_type), Box::new(result_type.clone()));
+                self.unify(&func_type, &expected)
+                    .map_err(|(index, containing)| self.infinite_type(index, &containing, term))?;
+                Ok(result_type)
+            }
+        }
+    }
+
+    /// The error of a failed occurs check, with both types written with the
+    /// same variable names.
+    fn infinite_type(&self, index: usize, containing: &Type, term: &LambdaTerm) -> TypeError {
+        let mut numbers = HashMap::new();
+        TypeError::InfiniteType {
+            variable: Type::Variable(index).renumbered(&mut numbers),
+            containing: self.resolve(containing).renumbered(&mut numbers),
+            term: term.clone(),
+        }
+    }
+}
+
+/// Infer the principal simple type of the term, of which every other type of
+/// the term is an instance.
+///
+/// Names bound in `definitions` have the type scheme of their definition,
+/// instantiated at each of their occurrences (let-polymorphism), and other
+/// free names have unknown types. The recursive definitions built by
+/// `let rec` have the type of their body, where their name is bound to that
+/// same type.
+/// Fails if the term has no simple type.
+pub fn infer(
+    term: &LambdaTerm,
+    definitions: &HashMap<String, LambdaTerm>,
+) -> Result<Type, TypeError> {
+    let mut inference = Inference {
+        definitions,
+        globals: HashMap::new(),
+        schemes: HashMap::new(),
+        substitution: HashMap::new(),
+        next_variable: 0,
+    };
+    let ty = inference.infer(term, &mut Vec::new())?;
+    Ok(inference.resolve(&ty).normalized())
+}
+
+/// Infer the type scheme of a definition, its principal type with all its
+/// variables quantified.
+pub fn scheme(
+    term: &LambdaTerm,
+    definitions: &HashMap<String, LambdaTerm>,
+) -> Result<Scheme, TypeError> {
+    infer(term, definitions).map(|ty| Scheme { ty })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::parse_term;
+
+    /// The type of a term, written as a string.
+    fn type_of(source: &str, definitions: &HashMap<String, LambdaTerm>) -> String {
+        infer(&parse_term(source), definitions)
+            .expect("the term should have a simple type")
+            .to_string()
+    }
+
+    #[test]
+    fn test_principal_types() {
+        let none = HashMap::new();
+        let cases = [
+            ("\\x. x", "a -> a"),
+            ("\\f x. f x", "(a -> b) -> a -> b"),
+            ("\\x y. x", "a -> b -> a"),
+            ("\\x y z. x z (y z)", "(a -> b -> c) -> (a -> b) -> a -> c"),
+            ("\\f g x. f (g x)", "(a -> b) -> (c -> a) -> c -> b"),
+            ("2", "(a -> a) -> a -> a"),
+            // Free names have the same unknown type at each occurrence
+            ("f x x", "a"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(type_of(source, &none), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_let_names_have_the_type_of_their_definition() {
+        let definitions = HashMap::from([
+            ("id".to_string(), parse_term("\\x. x")),
+            ("twice".to_string(), parse_term("\\f x. f (f x)")),
+            ("k".to_string(), parse_term("\\x y. x")),
+        ]);
+        assert_eq!(type_of("twice id", &definitions), "a -> a");
+
+        // A name can be used at several types
+        assert_eq!(type_of("k k", &definitions), "a -> b -> c -> b");
+        assert_eq!(
+            type_of("k (id 2) (id k)", &definitions),
+            "(a -> a) -> a -> a"
+        );
+        assert_eq!(
+            scheme(&parse_term("\\x y. x"), &definitions)
+                .expect("typable")
+                .to_string(),
+            "forall a b. a -> b -> a"
+        );
+
+        // A parameter cannot
+        assert!(infer(&parse_term("\\f. f f"), &definitions).is_err());
+    }
+
+    #[test]
+    fn test_recursive_definitions() {
+        let definitions = HashMap::from([
+            ("id".to_string(), parse_term("\\x. x")),
+            (
+                "loop".to_string(),
+                LambdaTerm::fixpoint("loop", parse_term("\\n f x. loop n f (id x)")),
+            ),
+        ]);
+        assert_eq!(type_of("loop", &definitions), "a -> b -> c -> d");
+        // The fixpoint combinator itself has no simple type
+        assert!(matches!(
+            infer(
+                &parse_term("\\f. (\\x. f (x x)) (\\y. f (y y))"),
+                &definitions
+            ),
+            Err(TypeError::InfiniteType { .. })
+        ));
+        assert!(
+            infer(
+                &parse_term("(\\f. (\\x. f (x x)) (\\x. f (x x))) (\\z. z)"),
+                &definitions
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_occurs_check() {
+        let error = infer(&parse_term("\\x. x x"), &HashMap::new()).expect_err("self-application");
+        assert_eq!(
+            error,
+            TypeError::InfiniteType {
+                variable: Type::Variable(0),
+                containing: Type::Arrow(Box::new(Type::Variable(0)), Box::new(Type::Variable(1))),
+                term: parse_term("x x"),
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "occurs check failed: `a` would have to be `a -> b`, an infinite type"
+        );
+    }
+}